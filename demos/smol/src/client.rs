@@ -52,7 +52,8 @@ accept: */*
                 .await?;
 
             let mut buf = vec![0; 64];
-            tls_stream.read(&mut buf).await?;
+            let n = tls_stream.read(&mut buf).await?;
+            buf.truncate(n);
 
             println!("{} {:?}", i, str::from_utf8(&buf));
 
@@ -1,16 +1,13 @@
 use std::env;
-use std::fs::File;
-use std::io::{self, BufReader};
+use std::io;
 use std::net::TcpListener;
 use std::path::PathBuf;
 use std::str;
-use std::sync::Arc;
 
 use futures::StreamExt;
 use futures::{AsyncReadExt, AsyncWriteExt};
 use smol::{Async, Task};
 
-use async_tls_lite::prelude::{pemfile, NoClientAuth, ServerConfig};
 use async_tls_lite::TlsAcceptor;
 
 /*
@@ -23,26 +20,11 @@ fn main() -> io::Result<()> {
 }
 
 async fn run() -> io::Result<()> {
-    let mut server_config = ServerConfig::new(NoClientAuth::new());
-
     let mkcert_path = PathBuf::new().join("mkcert");
-    let certs = pemfile::certs(&mut BufReader::new(File::open(
-        mkcert_path.join("tls.lvh.me.crt"),
-    )?))
-    .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid cert"))?;
-    let key = pemfile::pkcs8_private_keys(&mut BufReader::new(File::open(
-        mkcert_path.join("tls.lvh.me-key.pem"),
-    )?))
-    .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid key"))?
-    .first()
-    .expect("invalid key")
-    .to_owned();
-    server_config
-        .set_single_cert(certs, key)
-        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
-
-    //
-    let acceptor = TlsAcceptor::from(Arc::new(server_config));
+    let acceptor = TlsAcceptor::builder()
+        .cert_chain_pem_file(mkcert_path.join("tls.lvh.me.crt"))
+        .private_key_pem_file(mkcert_path.join("tls.lvh.me-key.pem"))
+        .build()?;
 
     let port: u16 = env::var("LISTEN_PORT")
         .unwrap_or_else(|_| "443".to_owned())
@@ -61,7 +43,8 @@ async fn run() -> io::Result<()> {
             let mut tls_stream = acceptor.accept(tcp_stream).await?;
 
             let mut buf = vec![0; 64];
-            tls_stream.read(&mut buf).await?;
+            let n = tls_stream.read(&mut buf).await?;
+            buf.truncate(n);
 
             println!("{:?}", str::from_utf8(&buf));
 
@@ -90,7 +73,10 @@ async fn run() -> io::Result<()> {
 mod tests {
     use super::*;
 
+    use std::fs::File;
+    use std::io::BufReader;
     use std::net::{SocketAddr, TcpStream};
+    use std::sync::Arc;
     use std::time::Duration;
 
     use smol::Timer;
@@ -135,7 +121,8 @@ mod tests {
 
                 tls_stream.write_all(b"GET / HTTP/1.1\r\n\r\n").await?;
                 let mut buf = vec![0; 64];
-                tls_stream.read(&mut buf).await?;
+                let n = tls_stream.read(&mut buf).await?;
+                buf.truncate(n);
                 println!("{:?}", str::from_utf8(&buf));
                 assert!(buf.starts_with(b"HTTP/1.1 200 OK\r\n\r\n"));
 
@@ -147,7 +134,8 @@ mod tests {
 
                 tls_stream.write_all(b"GET /foo HTTP/1.1\r\n\r\n").await?;
                 let mut buf = vec![0; 64];
-                tls_stream.read(&mut buf).await?;
+                let n = tls_stream.read(&mut buf).await?;
+                buf.truncate(n);
                 println!("{:?}", str::from_utf8(&buf));
                 assert!(buf.starts_with(b"HTTP/1.1 400 Bad Request\r\n\r\n"));
 
@@ -1,31 +1,416 @@
 // ref https://github.com/async-rs/async-tls/blob/v0.7.1/src/acceptor.rs
 
+use std::future::Future;
 use std::io;
-use std::sync::Arc;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use std::time::Duration;
 
-use futures_util::io::{AsyncRead, AsyncWrite};
-use rustls::{ServerConfig, ServerSession};
+use futures_util::future::FusedFuture;
+use futures_util::io::{AsyncRead, AsyncReadExt, AsyncWrite};
+use rustls::sign::{self, CertifiedKey};
+use rustls::{
+    AllowAnyAnonymousOrAuthenticatedClient, AllowAnyAuthenticatedClient, Certificate, NoClientAuth,
+    NoServerSessionStorage, PrivateKey, RootCertStore, ServerConfig, ServerSession, Session,
+    StoresServerSessions,
+};
 
-use crate::{server_handshake, TlsStream};
+use crate::error::Error;
+use crate::handshake::{Handshake, MidHandshake};
+use crate::pem::{invalid_input, load_certs, load_private_key};
+#[cfg(feature = "proxy-protocol")]
+use crate::proxy_protocol::{read_proxy_header, ProxyHeader};
+use crate::sniff::{is_tls_handshake, MaybeTls, PrefixedStream};
+use crate::timeout::{before_deadline, HandshakeTimeout, Timer};
+use crate::{HandshakeError, LruServerSessionStore, RotatingTicketer, SniCertResolver, TlsStream};
 
+/// Clones share the config, so `set_config` on one affects all of them.
 #[derive(Clone)]
 pub struct TlsAcceptor {
-    inner: Arc<ServerConfig>,
+    inner: Arc<RwLock<Arc<ServerConfig>>>,
+    handshake_timeout: Option<HandshakeTimeout>,
 }
 
 impl From<Arc<ServerConfig>> for TlsAcceptor {
     fn from(inner: Arc<ServerConfig>) -> TlsAcceptor {
-        TlsAcceptor { inner }
+        TlsAcceptor {
+            inner: Arc::new(RwLock::new(inner)),
+            handshake_timeout: None,
+        }
     }
 }
 
 impl TlsAcceptor {
-    pub async fn accept<S>(&self, stream: S) -> io::Result<TlsStream<ServerSession, S>>
+    pub fn builder() -> TlsAcceptorBuilder {
+        let mut config = ServerConfig::new(NoClientAuth::new());
+        config.session_storage = Arc::new(LruServerSessionStore::new(256));
+
+        TlsAcceptorBuilder {
+            config,
+            certs: vec![],
+            cert_chain_pem_file: None,
+            private_key_pem_file: None,
+            client_auth: None,
+            ticket_key_lifetime: None,
+            handshake_timeout: None,
+        }
+    }
+
+    /// Fails `accept` with `io::ErrorKind::TimedOut` when the handshake isn't done
+    /// before the future returned by `timer(duration)` completes.
+    pub fn with_handshake_timeout<F, T>(mut self, duration: Duration, timer: F) -> Self
+    where
+        F: Fn(Duration) -> T + Send + Sync + 'static,
+        T: Future + Send + 'static,
+    {
+        self.handshake_timeout = Some(HandshakeTimeout::new(duration, timer));
+        self
+    }
+
+    pub fn config(&self) -> Arc<ServerConfig> {
+        match self.inner.read() {
+            Ok(config) => config.clone(),
+            Err(err) => err.into_inner().clone(),
+        }
+    }
+
+    /// New handshakes use `config`, those already started keep the old one.
+    pub fn set_config(&self, config: Arc<ServerConfig>) {
+        match self.inner.write() {
+            Ok(mut inner) => *inner = config,
+            Err(err) => *err.into_inner() = config,
+        }
+    }
+
+    /// The handshake deadline, if any, starts when this is called.
+    pub fn accept<S>(&self, stream: S) -> Accept<S>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let session = ServerSession::new(&self.config());
+
+        Accept(MidHandshake::Handshaking(
+            Handshake::new(session, stream).with_timeout(self.handshake_timeout.as_ref()),
+        ))
+    }
+
+    /// Peeks the first byte, and only does the handshake when it's the content type of a
+    /// TLS handshake record, so legacy plaintext clients can share the port. The peek waits
+    /// for the client to send something, so protocols where the server speaks first don't
+    /// fit.
+    ///
+    /// The handshake deadline, if any, covers the peek too.
+    pub async fn accept_or_plaintext<S>(&self, mut stream: S) -> io::Result<MaybeTls<S>>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut timer = self.handshake_timer();
+
+        let mut peeked = [0; 1];
+        let len = before_deadline(timer.as_mut(), stream.read(&mut peeked)).await?;
+        let peeked = &peeked[..len];
+
+        if !is_tls_handshake(peeked) {
+            return Ok(MaybeTls::Plaintext(PrefixedStream::new(
+                peeked.to_vec(),
+                stream,
+            )));
+        }
+
+        let mut session = ServerSession::new(&self.config());
+        replay(&mut session, peeked)?;
+
+        Handshake::new(session, stream)
+            .replayed(peeked)
+            .with_timer(timer)
+            .await
+            .map(MaybeTls::Tls)
+            .map_err(Into::into)
+    }
+
+    /// Reads the PROXY protocol header a load balancer sends before the ClientHello, then
+    /// accepts. The header tells the real client address.
+    ///
+    /// The handshake deadline, if any, covers reading the header too.
+    #[cfg(feature = "proxy-protocol")]
+    pub async fn accept_with_proxy_header<S>(
+        &self,
+        mut stream: S,
+    ) -> io::Result<(ProxyHeader, TlsStream<ServerSession, S>)>
     where
         S: AsyncRead + AsyncWrite + Unpin,
     {
-        let session = ServerSession::new(&self.inner);
+        let mut timer = self.handshake_timer();
+
+        let header = before_deadline(timer.as_mut(), read_proxy_header(&mut stream)).await?;
+
+        let tls_stream = self.accept_with_timer(stream, timer).await?;
+
+        Ok((header, tls_stream))
+    }
+
+    /// To accept after reading something else first, e.g. a PROXY header: read it with
+    /// `before_deadline` on this timer, then hand the timer to `accept_with_timer`.
+    pub(crate) fn handshake_timer(&self) -> Option<Timer> {
+        self.handshake_timeout.as_ref().map(HandshakeTimeout::timer)
+    }
+
+    pub(crate) async fn accept_with_timer<S>(
+        &self,
+        stream: S,
+        timer: Option<Timer>,
+    ) -> io::Result<TlsStream<ServerSession, S>>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let session = ServerSession::new(&self.config());
+
+        Handshake::new(session, stream)
+            .with_timer(timer)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Like `accept`, plus the protocol negotiated by ALPN, to dispatch e.g. `h2` and
+    /// `http/1.1` connections to their handlers.
+    pub async fn accept_with_alpn<S>(
+        &self,
+        stream: S,
+    ) -> io::Result<(Option<Vec<u8>>, TlsStream<ServerSession, S>)>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let tls_stream = self.accept(stream).await?;
+        let alpn_protocol = tls_stream
+            .get_session_ref()
+            .get_alpn_protocol()
+            .map(|protocol| protocol.to_vec());
+
+        Ok((alpn_protocol, tls_stream))
+    }
+
+    /// Like `accept`, but hands the stream and the bytes read so far back on failure,
+    /// e.g. to answer a plaintext request with an error page.
+    pub async fn accept_with_fallback<S>(
+        &self,
+        stream: S,
+    ) -> Result<TlsStream<ServerSession, S>, HandshakeError<ServerSession, S>>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let session = ServerSession::new(&self.config());
+
+        Handshake::new(session, stream)
+            .record_read_bytes()
+            .with_timeout(self.handshake_timeout.as_ref())
+            .await
+    }
+}
+
+/// Feeds bytes already read from the transport to `session`.
+pub(crate) fn replay(session: &mut ServerSession, mut bytes: &[u8]) -> io::Result<()> {
+    while !bytes.is_empty() {
+        session.read_tls(&mut bytes)?;
+        session
+            .process_new_packets()
+            .map_err(|err| io::Error::from(Error::from(err)))?;
+    }
+
+    Ok(())
+}
+
+/// Builds a `TlsAcceptor` whose certificates are picked by a `SniCertResolver`.
+pub struct TlsAcceptorBuilder {
+    config: ServerConfig,
+    certs: Vec<(Option<String>, Vec<Certificate>, PrivateKey)>,
+    cert_chain_pem_file: Option<PathBuf>,
+    private_key_pem_file: Option<PathBuf>,
+    client_auth: Option<ClientAuth>,
+    ticket_key_lifetime: Option<Duration>,
+    handshake_timeout: Option<HandshakeTimeout>,
+}
+
+enum ClientAuth {
+    Optional(PathBuf),
+    Required(PathBuf),
+}
+
+impl TlsAcceptorBuilder {
+    /// The default cert chain, leaf first. Needs `private_key_pem_file`.
+    pub fn cert_chain_pem_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.cert_chain_pem_file = Some(path.into());
+        self
+    }
+
+    /// A PKCS#8, PKCS#1 (RSA) or SEC1 (EC) key. Needs `cert_chain_pem_file`.
+    pub fn private_key_pem_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.private_key_pem_file = Some(path.into());
+        self
+    }
+
+    /// `name` is a hostname like `example.com` or a pattern like `*.example.com`.
+    pub fn cert(
+        mut self,
+        name: impl Into<String>,
+        cert_chain: Vec<Certificate>,
+        key: PrivateKey,
+    ) -> Self {
+        self.certs.push((Some(name.into()), cert_chain, key));
+        self
+    }
+
+    /// Used when the client sends no SNI or no other certificate matches.
+    pub fn default_cert(mut self, cert_chain: Vec<Certificate>, key: PrivateKey) -> Self {
+        self.certs.push((None, cert_chain, key));
+        self
+    }
+
+    /// Verifies a client cert against the CA certs in the file if the client sends one.
+    pub fn client_auth_optional(mut self, ca_pem_file: impl Into<PathBuf>) -> Self {
+        self.client_auth = Some(ClientAuth::Optional(ca_pem_file.into()));
+        self
+    }
+
+    /// Fails the handshake unless the client sends a cert issued by a CA in the file.
+    pub fn client_auth_required(mut self, ca_pem_file: impl Into<PathBuf>) -> Self {
+        self.client_auth = Some(ClientAuth::Required(ca_pem_file.into()));
+        self
+    }
+
+    /// In preference order, e.g. `vec![b"h2".to_vec(), b"http/1.1".to_vec()]`.
+    pub fn alpn_protocols(mut self, protocols: Vec<Vec<u8>>) -> Self {
+        self.config.set_protocols(&protocols);
+        self
+    }
+
+    /// Issues stateless session tickets encrypted by a `RotatingTicketer`, which replaces
+    /// its key every `key_lifetime`.
+    pub fn session_tickets(mut self, key_lifetime: Duration) -> Self {
+        self.ticket_key_lifetime = Some(key_lifetime);
+        self
+    }
+
+    /// Keeps up to `size` sessions for resumption by session ID, least recently used
+    /// evicted first, 256 by default. `0` turns the cache off.
+    pub fn session_cache_size(self, size: usize) -> Self {
+        if size == 0 {
+            self.session_store(Arc::new(NoServerSessionStorage {}))
+        } else {
+            self.session_store(Arc::new(LruServerSessionStore::new(size)))
+        }
+    }
+
+    /// Backs the session cache with custom storage, e.g. one shared between servers.
+    pub fn session_store(mut self, store: Arc<dyn StoresServerSessions>) -> Self {
+        self.config.session_storage = store;
+        self
+    }
+
+    pub fn handshake_timeout<F, T>(mut self, duration: Duration, timer: F) -> Self
+    where
+        F: Fn(Duration) -> T + Send + Sync + 'static,
+        T: Future + Send + 'static,
+    {
+        self.handshake_timeout = Some(HandshakeTimeout::new(duration, timer));
+        self
+    }
+
+    pub fn build(mut self) -> io::Result<TlsAcceptor> {
+        let mut config = self.config;
+
+        match (self.cert_chain_pem_file, self.private_key_pem_file) {
+            (Some(cert_chain_path), Some(private_key_path)) => {
+                let cert_chain = load_certs(&cert_chain_path)?;
+                let key = load_private_key(&private_key_path)?;
+                self.certs.push((None, cert_chain, key));
+            }
+            (Some(_), None) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "cert_chain_pem_file is set without private_key_pem_file",
+                ))
+            }
+            (None, Some(_)) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "private_key_pem_file is set without cert_chain_pem_file",
+                ))
+            }
+            (None, None) => {}
+        }
+
+        if self.certs.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no certificate configured",
+            ));
+        }
+
+        let mut resolver = SniCertResolver::new();
+        for (name, cert_chain, key) in self.certs {
+            let key = sign::any_supported_type(&key)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid key"))?;
+            let key = CertifiedKey::new(cert_chain, Arc::new(key));
+
+            match name {
+                Some(name) => resolver.add(&name, key)?,
+                None => resolver.set_default(key)?,
+            }
+        }
+        config.cert_resolver = Arc::new(resolver);
+
+        if let Some(client_auth) = self.client_auth {
+            let (path, required) = match &client_auth {
+                ClientAuth::Optional(path) => (path, false),
+                ClientAuth::Required(path) => (path, true),
+            };
+
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(path)? {
+                roots
+                    .add(&cert)
+                    .map_err(|err| invalid_input(path, &format!("invalid CA cert: {}", err)))?;
+            }
+
+            config.set_client_certificate_verifier(if required {
+                AllowAnyAuthenticatedClient::new(roots)
+            } else {
+                AllowAnyAnonymousOrAuthenticatedClient::new(roots)
+            });
+        }
+
+        if let Some(key_lifetime) = self.ticket_key_lifetime {
+            config.ticketer = Arc::new(RotatingTicketer::new(key_lifetime)?);
+        }
+
+        Ok(TlsAcceptor {
+            inner: Arc::new(RwLock::new(Arc::new(config))),
+            handshake_timeout: self.handshake_timeout,
+        })
+    }
+}
+
+/// Future returned by `TlsAcceptor::accept`.
+pub struct Accept<S>(pub(crate) MidHandshake<ServerSession, S>);
+
+impl<S> Future for Accept<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    type Output = io::Result<TlsStream<ServerSession, S>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        Pin::new(&mut self.get_mut().0).poll(cx)
+    }
+}
 
-        server_handshake(session, stream).await
+impl<S> FusedFuture for Accept<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn is_terminated(&self) -> bool {
+        self.0.is_terminated()
     }
 }
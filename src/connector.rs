@@ -1,22 +1,48 @@
 // ref https://github.com/async-rs/async-tls/blob/v0.7.1/src/connector.rs
 
+use std::future::Future;
 use std::io;
+use std::path::PathBuf;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
 
+use futures_util::future::FusedFuture;
 use futures_util::io::{AsyncRead, AsyncWrite};
-use rustls::{ClientConfig, ClientSession};
+use rustls::{
+    ClientConfig, ClientSession, NoClientSessionStorage, ServerCertVerifier, StoresClientSessions,
+};
 use webpki::DNSNameRef;
 
-use crate::{client_handshake, TlsStream};
+use crate::error::Error;
+use crate::handshake::{Handshake, MidHandshake};
+use crate::pem::{invalid_input, load_certs, load_certs_from_dir, load_private_key};
+use crate::pinning::{parse_pin, PinningVerifier};
+use crate::server_name::ip_placeholder;
+use crate::session_store::LruClientSessionStore;
+use crate::timeout::HandshakeTimeout;
+use crate::verify::ServerVerifier;
+use crate::{HandshakeError, IntoServerName, ServerName, TlsStream};
 
 #[derive(Clone)]
 pub struct TlsConnector {
     inner: Arc<ClientConfig>,
+    inner_without_sni: Arc<ClientConfig>,
+    handshake_timeout: Option<HandshakeTimeout>,
+    #[cfg(feature = "dangerous")]
+    danger: Option<&'static str>,
 }
 
 impl From<Arc<ClientConfig>> for TlsConnector {
     fn from(inner: Arc<ClientConfig>) -> TlsConnector {
-        TlsConnector { inner }
+        TlsConnector {
+            inner_without_sni: without_sni(&inner),
+            inner,
+            handshake_timeout: None,
+            #[cfg(feature = "dangerous")]
+            danger: None,
+        }
     }
 }
 
@@ -26,35 +52,379 @@ impl Default for TlsConnector {
         config
             .root_store
             .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+        #[cfg(feature = "native-roots")]
+        crate::native_roots::add_native_roots_or_warn(&mut config.root_store);
+        config
+            .dangerous()
+            .set_certificate_verifier(Arc::new(ServerVerifier::default()));
         Arc::new(config).into()
     }
 }
 
+fn without_sni(config: &ClientConfig) -> Arc<ClientConfig> {
+    let mut config = config.clone();
+    config.enable_sni = false;
+    Arc::new(config)
+}
+
 impl TlsConnector {
     pub fn new() -> Self {
         Default::default()
     }
 
-    pub async fn connect<S>(
+    pub fn builder() -> TlsConnectorBuilder {
+        let mut config = ClientConfig::new();
+        config.session_persistence = Arc::new(LruClientSessionStore::new(32));
+
+        TlsConnectorBuilder {
+            config,
+            root_cert_pem_files: vec![],
+            root_cert_pem_dirs: vec![],
+            client_cert_pem_file: None,
+            client_private_key_pem_file: None,
+            spki_pins: vec![],
+            webpki_roots: true,
+            #[cfg(feature = "native-roots")]
+            native_roots: None,
+            #[cfg(feature = "dangerous")]
+            danger_accept_invalid_certs: false,
+            #[cfg(feature = "dangerous")]
+            danger_accept_invalid_hostnames: false,
+            handshake_timeout: None,
+        }
+    }
+
+    #[cfg(feature = "dangerous")]
+    fn warn_danger(&self, server_name: &dyn std::fmt::Display) {
+        if let Some(danger) = self.danger {
+            log::warn!("connecting to {} with {}", server_name, danger);
+        }
+    }
+
+    fn session(&self, server_name: impl IntoServerName) -> io::Result<ClientSession> {
+        let server_name = server_name.into_server_name()?;
+
+        #[cfg(feature = "dangerous")]
+        self.warn_danger(&server_name);
+
+        let (config, dns_name) = match server_name {
+            ServerName::DnsName(name) => (&self.inner, name),
+            ServerName::IpAddress(ip) => (&self.inner_without_sni, ip_placeholder(ip)),
+        };
+
+        let dns_name = DNSNameRef::try_from_ascii_str(&dns_name)
+            .map_err(|_| io::Error::from(Error::InvalidServerName))?;
+
+        Ok(ClientSession::new(config, dns_name))
+    }
+
+    /// Fails `connect` with `io::ErrorKind::TimedOut` when the handshake isn't done
+    /// before the future returned by `timer(duration)` completes.
+    pub fn with_handshake_timeout<F, T>(mut self, duration: Duration, timer: F) -> Self
+    where
+        F: Fn(Duration) -> T + Send + Sync + 'static,
+        T: Future + Send + 'static,
+    {
+        self.handshake_timeout = Some(HandshakeTimeout::new(duration, timer));
+        self
+    }
+
+    /// `server_name` is a DNS name or an IP address, e.g. `"example.com"` or `"127.0.0.1"`.
+    /// IP addresses need the verifier of `TlsConnector::builder()` or `TlsConnector::default()`.
+    ///
+    /// The handshake deadline, if any, starts when this is called.
+    pub fn connect<S>(&self, server_name: impl IntoServerName, stream: S) -> Connect<S>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let session = match self.session(server_name) {
+            Ok(session) => session,
+            Err(err) => return Connect(MidHandshake::Error(err)),
+        };
+
+        Connect(MidHandshake::Handshaking(
+            Handshake::new(session, stream).with_timeout(self.handshake_timeout.as_ref()),
+        ))
+    }
+
+    /// Like `connect`, but hands the stream and the bytes read so far back on a failed
+    /// handshake, e.g. to fall back to another protocol on the same connection.
+    ///
+    /// An invalid `server_name` fails before the stream is used, with a session that never
+    /// handshook.
+    pub async fn connect_with_fallback<S>(
         &self,
-        domain: impl AsRef<str>,
+        server_name: impl IntoServerName,
         stream: S,
-    ) -> io::Result<TlsStream<ClientSession, S>>
+    ) -> Result<TlsStream<ClientSession, S>, HandshakeError<ClientSession, S>>
     where
         S: AsyncRead + AsyncWrite + Unpin,
     {
-        let domain = match DNSNameRef::try_from_ascii_str(domain.as_ref()) {
-            Ok(domain) => domain,
-            Err(_) => {
+        let session = match self.session(server_name) {
+            Ok(session) => session,
+            Err(error) => {
+                let unused = DNSNameRef::try_from_ascii_str("invalid").expect("never");
+                return Err(HandshakeError {
+                    error,
+                    session: ClientSession::new(&self.inner_without_sni, unused),
+                    stream,
+                    read_bytes: vec![],
+                });
+            }
+        };
+
+        Handshake::new(session, stream)
+            .record_read_bytes()
+            .with_timeout(self.handshake_timeout.as_ref())
+            .await
+    }
+}
+
+/// Builds a `TlsConnector`, trusting the webpki roots (and with the `native-roots`
+/// feature the OS trust store) unless turned off.
+pub struct TlsConnectorBuilder {
+    config: ClientConfig,
+    root_cert_pem_files: Vec<PathBuf>,
+    root_cert_pem_dirs: Vec<PathBuf>,
+    client_cert_pem_file: Option<PathBuf>,
+    client_private_key_pem_file: Option<PathBuf>,
+    spki_pins: Vec<String>,
+    webpki_roots: bool,
+    #[cfg(feature = "native-roots")]
+    native_roots: Option<bool>,
+    #[cfg(feature = "dangerous")]
+    danger_accept_invalid_certs: bool,
+    #[cfg(feature = "dangerous")]
+    danger_accept_invalid_hostnames: bool,
+    handshake_timeout: Option<HandshakeTimeout>,
+}
+
+impl TlsConnectorBuilder {
+    /// Trusts every certificate in the file, e.g. a private CA like mkcert's `rootCA.pem`.
+    pub fn root_cert_pem_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.root_cert_pem_files.push(path.into());
+        self
+    }
+
+    /// Trusts every certificate in the `*.pem` and `*.crt` files of the directory.
+    pub fn root_cert_pem_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.root_cert_pem_dirs.push(path.into());
+        self
+    }
+
+    /// The cert chain presented when the server asks for one, leaf first.
+    /// Needs `client_private_key_pem_file`.
+    pub fn client_cert_pem_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.client_cert_pem_file = Some(path.into());
+        self
+    }
+
+    /// A PKCS#8, PKCS#1 (RSA) or SEC1 (EC) key. Needs `client_cert_pem_file`.
+    pub fn client_private_key_pem_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.client_private_key_pem_file = Some(path.into());
+        self
+    }
+
+    /// Requires the server's cert, an intermediate that issued it or a configured root its
+    /// chain verifies to, to have a SubjectPublicKeyInfo with this base64 SHA-256 hash,
+    /// like an HPKP `pin-sha256`. Any of several pins may match.
+    pub fn pin_spki_sha256(mut self, pin: impl Into<String>) -> Self {
+        self.spki_pins.push(pin.into());
+        self
+    }
+
+    pub fn webpki_roots(mut self, enabled: bool) -> Self {
+        self.webpki_roots = enabled;
+        self
+    }
+
+    /// Whether to trust the OS trust store. On by default, when nothing can be loaded
+    /// from it that is logged and `build` goes on without it; set explicitly to `true`,
+    /// `build` fails instead.
+    #[cfg(feature = "native-roots")]
+    pub fn native_roots(mut self, enabled: bool) -> Self {
+        self.native_roots = Some(enabled);
+        self
+    }
+
+    /// Skips all server cert checks, only for local development. Pins still apply.
+    /// Logs a warning on each connection.
+    #[cfg(feature = "dangerous")]
+    pub fn danger_accept_invalid_certs(mut self, enabled: bool) -> Self {
+        self.danger_accept_invalid_certs = enabled;
+        self
+    }
+
+    /// Skips the server name check of the server cert, only for local development.
+    /// Logs a warning on each connection.
+    #[cfg(feature = "dangerous")]
+    pub fn danger_accept_invalid_hostnames(mut self, enabled: bool) -> Self {
+        self.danger_accept_invalid_hostnames = enabled;
+        self
+    }
+
+    /// In preference order, e.g. `vec![b"h2".to_vec(), b"http/1.1".to_vec()]`.
+    pub fn alpn_protocols(mut self, protocols: Vec<Vec<u8>>) -> Self {
+        self.config.alpn_protocols = protocols;
+        self
+    }
+
+    /// Keeps up to `size` entries for resuming sessions, least recently used evicted first,
+    /// 32 by default. `0` turns resumption off.
+    pub fn session_cache_size(self, size: usize) -> Self {
+        if size == 0 {
+            self.session_store(Arc::new(NoClientSessionStorage {}))
+        } else {
+            self.session_store(Arc::new(LruClientSessionStore::new(size)))
+        }
+    }
+
+    /// Shares sessions with other connectors, e.g. an `LruClientSessionStore`.
+    pub fn session_store(mut self, store: Arc<dyn StoresClientSessions>) -> Self {
+        self.config.session_persistence = store;
+        self
+    }
+
+    pub fn handshake_timeout<F, T>(mut self, duration: Duration, timer: F) -> Self
+    where
+        F: Fn(Duration) -> T + Send + Sync + 'static,
+        T: Future + Send + 'static,
+    {
+        self.handshake_timeout = Some(HandshakeTimeout::new(duration, timer));
+        self
+    }
+
+    pub fn build(self) -> io::Result<TlsConnector> {
+        let mut config = self.config;
+
+        if self.webpki_roots {
+            config
+                .root_store
+                .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+        }
+
+        #[cfg(feature = "native-roots")]
+        {
+            match self.native_roots {
+                Some(true) => {
+                    crate::native_roots::add_native_roots(&mut config.root_store)?;
+                }
+                Some(false) => {}
+                None => crate::native_roots::add_native_roots_or_warn(&mut config.root_store),
+            }
+        }
+
+        let files = self
+            .root_cert_pem_files
+            .iter()
+            .map(|path| (path, load_certs(path)));
+        let dirs = self
+            .root_cert_pem_dirs
+            .iter()
+            .map(|path| (path, load_certs_from_dir(path)));
+        for (path, certs) in files.chain(dirs) {
+            for cert in certs? {
+                config
+                    .root_store
+                    .add(&cert)
+                    .map_err(|err| invalid_input(path, &format!("invalid root cert: {}", err)))?;
+            }
+        }
+
+        match (self.client_cert_pem_file, self.client_private_key_pem_file) {
+            (Some(cert_chain_path), Some(private_key_path)) => {
+                let cert_chain = load_certs(&cert_chain_path)?;
+                let key = load_private_key(&private_key_path)?;
+                config
+                    .set_single_client_cert(cert_chain, key)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+            }
+            (Some(_), None) => {
                 return Err(io::Error::new(
                     io::ErrorKind::InvalidInput,
-                    "invalid domain",
-                ));
+                    "client_cert_pem_file is set without client_private_key_pem_file",
+                ))
             }
+            (None, Some(_)) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "client_private_key_pem_file is set without client_cert_pem_file",
+                ))
+            }
+            (None, None) => {}
+        }
+
+        #[cfg(feature = "dangerous")]
+        let danger = match (
+            self.danger_accept_invalid_certs,
+            self.danger_accept_invalid_hostnames,
+        ) {
+            (true, _) => Some("invalid certs accepted"),
+            (false, true) => Some("invalid hostnames accepted"),
+            (false, false) => None,
+        };
+        #[cfg(feature = "dangerous")]
+        let server_verifier = ServerVerifier {
+            accept_invalid_certs: self.danger_accept_invalid_certs,
+            accept_invalid_hostnames: self.danger_accept_invalid_hostnames,
         };
+        #[cfg(not(feature = "dangerous"))]
+        let server_verifier = ServerVerifier::default();
+        let requires_roots = !server_verifier.accept_invalid_certs;
+
+        let mut verifier: Arc<dyn ServerCertVerifier> = Arc::new(server_verifier);
+
+        if !self.spki_pins.is_empty() {
+            let pins = self
+                .spki_pins
+                .iter()
+                .map(|pin| parse_pin(pin))
+                .collect::<io::Result<_>>()?;
 
-        let session = ClientSession::new(&self.inner, domain);
+            verifier = Arc::new(PinningVerifier {
+                inner: verifier,
+                pins,
+            });
+        }
+
+        config.dangerous().set_certificate_verifier(verifier);
+
+        if requires_roots && config.root_store.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no root cert configured",
+            ));
+        }
+
+        Ok(TlsConnector {
+            inner_without_sni: without_sni(&config),
+            inner: Arc::new(config),
+            handshake_timeout: self.handshake_timeout,
+            #[cfg(feature = "dangerous")]
+            danger,
+        })
+    }
+}
+
+/// Future returned by `TlsConnector::connect`.
+pub struct Connect<S>(MidHandshake<ClientSession, S>);
+
+impl<S> Future for Connect<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    type Output = io::Result<TlsStream<ClientSession, S>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        Pin::new(&mut self.get_mut().0).poll(cx)
+    }
+}
 
-        client_handshake(session, stream).await
+impl<S> FusedFuture for Connect<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn is_terminated(&self) -> bool {
+        self.0.is_terminated()
     }
 }
@@ -0,0 +1,105 @@
+use std::error;
+use std::fmt;
+use std::io;
+
+use rustls::internal::msgs::enums::AlertDescription;
+use rustls::TLSError;
+
+/// The `TLSError::General` message `PinningVerifier` fails with, and how
+/// `Error::PinMismatch` displays.
+pub(crate) const PIN_MISMATCH: &str = "certificate pin mismatch";
+
+/// In the `io::Error`s from `connect` and `accept`, e.g.
+/// `err.get_ref().and_then(|err| err.downcast_ref::<Error>())`, or converted with
+/// `Error::from(err)`.
+#[derive(Debug)]
+pub enum Error {
+    /// Neither a DNS name nor an IP address.
+    InvalidServerName,
+    /// The peer's cert chain failed verification.
+    CertificateInvalid {
+        reason: String,
+    },
+    /// No cert in the verified chain matches a pin. A custom verifier failing with
+    /// `TLSError::General("certificate pin mismatch")` is reported as this too.
+    PinMismatch,
+    /// The peer aborted with a fatal alert.
+    PeerAlert(AlertDescription),
+    HandshakeTimeout,
+    /// Any other TLS failure.
+    Tls(TLSError),
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidServerName => write!(f, "invalid server name"),
+            Error::CertificateInvalid { reason } => write!(f, "invalid certificate: {}", reason),
+            Error::PinMismatch => write!(f, "{}", PIN_MISMATCH),
+            Error::PeerAlert(alert) => write!(f, "received fatal alert: {:?}", alert),
+            Error::HandshakeTimeout => write!(f, "tls handshake timed out"),
+            Error::Tls(err) => err.fmt(f),
+            Error::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Tls(err) => Some(err),
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<TLSError> for Error {
+    fn from(err: TLSError) -> Self {
+        match err {
+            TLSError::AlertReceived(alert) => Error::PeerAlert(alert),
+            TLSError::WebPKIError(err) => Error::CertificateInvalid {
+                reason: err.to_string(),
+            },
+            TLSError::NoCertificatesPresented => Error::CertificateInvalid {
+                reason: "no certificates presented".to_owned(),
+            },
+            TLSError::General(ref msg) if msg == PIN_MISMATCH => Error::PinMismatch,
+            err => Error::Tls(err),
+        }
+    }
+}
+
+/// Unwraps an `Error` or a `TLSError` inside `err`, anything else becomes `Error::Io`.
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        match err.get_ref() {
+            Some(inner) if inner.is::<Error>() => {
+                *err.into_inner().unwrap().downcast::<Error>().unwrap()
+            }
+            Some(inner) if inner.is::<TLSError>() => {
+                Error::from(*err.into_inner().unwrap().downcast::<TLSError>().unwrap())
+            }
+            _ => Error::Io(err),
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(err: Error) -> io::Error {
+        let kind = match err {
+            Error::Io(err) => return err,
+            Error::InvalidServerName => io::ErrorKind::InvalidInput,
+            Error::HandshakeTimeout => io::ErrorKind::TimedOut,
+            _ => io::ErrorKind::InvalidData,
+        };
+
+        io::Error::new(kind, err)
+    }
+}
+
+/// Puts an `Error` in place of a `TLSError` inside `err`.
+pub(crate) fn typed(err: io::Error) -> io::Error {
+    Error::from(err).into()
+}
@@ -0,0 +1,309 @@
+use std::error;
+use std::fmt;
+use std::future::Future;
+use std::io::{self, Read, Write};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use async_stream_packed::SyncableWithContextAsyncStream;
+use futures_util::future::FusedFuture;
+use futures_util::io::{AsyncRead, AsyncWrite};
+use futures_util::ready;
+use rustls::{ClientSession, ServerSession, Session};
+
+use crate::error::typed;
+#[cfg(any(feature = "acceptor", feature = "connector"))]
+use crate::error::Error;
+use crate::hello::{self, MAX_RECORD_LEN};
+#[cfg(any(feature = "acceptor", feature = "connector"))]
+use crate::timeout::{HandshakeTimeout, Timer};
+use crate::TlsStream;
+
+pub async fn client_handshake<S>(
+    session: ClientSession,
+    stream: S,
+) -> io::Result<TlsStream<ClientSession, S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    MidHandshake::Handshaking(Handshake::new(session, stream)).await
+}
+
+pub async fn server_handshake<S>(
+    session: ServerSession,
+    stream: S,
+) -> io::Result<TlsStream<ServerSession, S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    MidHandshake::Handshaking(Handshake::new(session, stream)).await
+}
+
+/// A failed handshake, with everything needed to keep using the transport.
+pub struct HandshakeError<SESS, S> {
+    pub error: io::Error,
+    pub session: SESS,
+    pub stream: S,
+    /// Bytes read from the transport during the handshake, only recorded by the
+    /// `*_with_fallback` variants.
+    pub read_bytes: Vec<u8>,
+}
+
+impl<SESS, S> fmt::Debug for HandshakeError<SESS, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HandshakeError")
+            .field("error", &self.error)
+            .field("read_bytes", &self.read_bytes)
+            .finish()
+    }
+}
+
+impl<SESS, S> fmt::Display for HandshakeError<SESS, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl<SESS, S> error::Error for HandshakeError<SESS, S> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl<SESS, S> From<HandshakeError<SESS, S>> for io::Error {
+    fn from(err: HandshakeError<SESS, S>) -> io::Error {
+        err.error
+    }
+}
+
+pub(crate) struct Handshake<SESS, S> {
+    inner: Option<(SESS, S)>,
+    read_bytes: Option<Vec<u8>>,
+    transcript: Transcript,
+    #[cfg(any(feature = "acceptor", feature = "connector"))]
+    timer: Option<Timer>,
+}
+
+impl<SESS, S> Handshake<SESS, S> {
+    pub(crate) fn new(session: SESS, stream: S) -> Self {
+        Self {
+            inner: Some((session, stream)),
+            read_bytes: None,
+            transcript: Default::default(),
+            #[cfg(any(feature = "acceptor", feature = "connector"))]
+            timer: None,
+        }
+    }
+
+    #[cfg(any(feature = "acceptor", feature = "connector"))]
+    pub(crate) fn with_timeout(mut self, handshake_timeout: Option<&HandshakeTimeout>) -> Self {
+        self.timer = handshake_timeout.map(HandshakeTimeout::timer);
+        self
+    }
+
+    #[cfg(feature = "acceptor")]
+    pub(crate) fn with_timer(mut self, timer: Option<Timer>) -> Self {
+        self.timer = timer;
+        self
+    }
+
+    /// For bytes fed to the session before the handshake, so the transcript starts with them.
+    #[cfg(feature = "acceptor")]
+    pub(crate) fn replayed(mut self, bytes: &[u8]) -> Self {
+        Transcript::extend(&mut self.transcript.read, bytes);
+        self
+    }
+
+    #[cfg(any(feature = "acceptor", feature = "connector"))]
+    pub(crate) fn record_read_bytes(mut self) -> Self {
+        self.read_bytes = Some(vec![]);
+        self
+    }
+}
+
+impl<SESS, S> Future for Handshake<SESS, S>
+where
+    SESS: Session + Unpin,
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    type Output = Result<TlsStream<SESS, S>, HandshakeError<SESS, S>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        let (mut session, mut stream) = this.inner.take().expect("never");
+
+        let mut sync_stream = Recorder {
+            inner: SyncableWithContextAsyncStream::new(&mut stream, cx),
+            read_bytes: this.read_bytes.as_mut(),
+            transcript: &mut this.transcript,
+        };
+
+        match complete_io(&mut session, &mut sync_stream) {
+            Ok(_) => {
+                let resumed = hello::is_resumed(
+                    &this.transcript.read,
+                    &this.transcript.written,
+                    session.get_protocol_version(),
+                );
+
+                let mut tls_stream = TlsStream::new(session, stream);
+                tls_stream.inner.resumed = resumed;
+
+                Poll::Ready(Ok(tls_stream))
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {
+                #[cfg(any(feature = "acceptor", feature = "connector"))]
+                {
+                    if let Some(timer) = this.timer.as_mut() {
+                        if timer.as_mut().poll(cx).is_ready() {
+                            this.timer = None;
+
+                            return Poll::Ready(Err(HandshakeError {
+                                error: Error::HandshakeTimeout.into(),
+                                session,
+                                stream,
+                                read_bytes: this.read_bytes.take().unwrap_or_default(),
+                            }));
+                        }
+                    }
+                }
+
+                this.inner = Some((session, stream));
+
+                Poll::Pending
+            }
+            Err(error) => Poll::Ready(Err(HandshakeError {
+                error: typed(error),
+                session,
+                stream,
+                read_bytes: this.read_bytes.take().unwrap_or_default(),
+            })),
+        }
+    }
+}
+
+/// Drives a handshake to an `io::Result`, can be polled again after it completed and
+/// then only returns an error.
+pub(crate) enum MidHandshake<SESS, S> {
+    Handshaking(Handshake<SESS, S>),
+    #[cfg_attr(
+        not(any(feature = "acceptor", feature = "connector")),
+        allow(dead_code)
+    )]
+    Error(io::Error),
+    End,
+}
+
+impl<SESS, S> Future for MidHandshake<SESS, S>
+where
+    SESS: Session + Unpin,
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    type Output = io::Result<TlsStream<SESS, S>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        let ret = match this {
+            MidHandshake::Handshaking(handshake) => {
+                ready!(Pin::new(handshake).poll(cx)).map_err(Into::into)
+            }
+            MidHandshake::Error(_) => match std::mem::replace(this, MidHandshake::End) {
+                MidHandshake::Error(err) => Err(err),
+                _ => unreachable!(),
+            },
+            MidHandshake::End => Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "tls handshake polled after completion",
+            )),
+        };
+
+        *this = MidHandshake::End;
+
+        Poll::Ready(ret)
+    }
+}
+
+impl<SESS, S> FusedFuture for MidHandshake<SESS, S>
+where
+    SESS: Session + Unpin,
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn is_terminated(&self) -> bool {
+        matches!(self, MidHandshake::End)
+    }
+}
+
+/// `Session::complete_io` returns as soon as the handshake is over, which can leave the last
+/// flight (e.g. the TLS 1.3 client Finished) unsent.
+fn complete_io<SESS, T>(session: &mut SESS, io: &mut T) -> io::Result<()>
+where
+    SESS: Session,
+    T: Read + Write,
+{
+    if session.is_handshaking() {
+        session.complete_io(io)?;
+    }
+
+    while session.wants_write() {
+        if session.write_tls(io)? == 0 {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+    }
+
+    Ok(())
+}
+
+/// The start of what was read and written, enough for the hellos.
+#[derive(Default)]
+struct Transcript {
+    read: Vec<u8>,
+    written: Vec<u8>,
+}
+
+impl Transcript {
+    fn extend(buf: &mut Vec<u8>, bytes: &[u8]) {
+        let n = bytes.len().min(MAX_RECORD_LEN.saturating_sub(buf.len()));
+        buf.extend_from_slice(&bytes[..n]);
+    }
+}
+
+struct Recorder<'a, T> {
+    inner: T,
+    read_bytes: Option<&'a mut Vec<u8>>,
+    transcript: &'a mut Transcript,
+}
+
+impl<'a, T> Read for Recorder<'a, T>
+where
+    T: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+
+        if let Some(read_bytes) = self.read_bytes.as_mut() {
+            read_bytes.extend_from_slice(&buf[..n]);
+        }
+        Transcript::extend(&mut self.transcript.read, &buf[..n]);
+
+        Ok(n)
+    }
+}
+
+impl<'a, T> Write for Recorder<'a, T>
+where
+    T: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+
+        Transcript::extend(&mut self.transcript.written, &buf[..n]);
+
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
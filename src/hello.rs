@@ -0,0 +1,104 @@
+use rustls::internal::msgs::codec::{u24, Codec, Reader};
+use rustls::internal::msgs::enums::{ContentType, HandshakeType};
+use rustls::internal::msgs::handshake::{
+    ClientHelloPayload, HandshakeMessagePayload, HandshakePayload, ServerHelloPayload,
+};
+use rustls::ProtocolVersion;
+
+/// Record header length.
+pub(crate) const RECORD_HEADER_LEN: usize = 5;
+/// Max record length, header included.
+pub(crate) const MAX_RECORD_LEN: usize = RECORD_HEADER_LEN + 16384;
+
+/// The plaintext handshake messages at the start of a flight, until the first record
+/// that isn't a handshake (or change_cipher_spec) record.
+pub(crate) fn plaintext_handshake_messages(bytes: &[u8]) -> Vec<Vec<u8>> {
+    let mut payload = vec![];
+
+    let mut r = Reader::init(bytes);
+    while let Some(header) = r.take(RECORD_HEADER_LEN) {
+        let typ = ContentType::read_bytes(&header[..1]);
+        let len = u16::from_be_bytes([header[3], header[4]]) as usize;
+        let fragment = match r.take(len) {
+            Some(fragment) => fragment,
+            None => break,
+        };
+
+        match typ {
+            Some(ContentType::Handshake) => payload.extend_from_slice(fragment),
+            Some(ContentType::ChangeCipherSpec) => {}
+            _ => break,
+        }
+    }
+
+    let mut messages = vec![];
+
+    let mut r = Reader::init(&payload);
+    while r.left() >= 4 {
+        let header = r.take(4).expect("never").to_vec();
+        let len = u24::decode(&header[1..]).expect("never").0 as usize;
+        match r.take(len) {
+            Some(body) => {
+                let mut message = header;
+                message.extend_from_slice(body);
+                messages.push(message)
+            }
+            None => break,
+        }
+    }
+
+    messages
+}
+
+pub(crate) fn read_client_hello(message: &[u8]) -> Option<ClientHelloPayload> {
+    match read_handshake_message(message, HandshakeType::ClientHello)? {
+        HandshakePayload::ClientHello(payload) => Some(payload),
+        _ => None,
+    }
+}
+
+pub(crate) fn read_server_hello(message: &[u8]) -> Option<ServerHelloPayload> {
+    match read_handshake_message(message, HandshakeType::ServerHello)? {
+        HandshakePayload::ServerHello(payload) => Some(payload),
+        _ => None,
+    }
+}
+
+fn read_handshake_message(message: &[u8], typ: HandshakeType) -> Option<HandshakePayload> {
+    if HandshakeType::read_bytes(message.get(..1)?)? != typ {
+        return None;
+    }
+
+    let mut r = Reader::init(message);
+    HandshakeMessagePayload::read_version(&mut r, ProtocolVersion::TLSv1_2).map(|m| m.payload)
+}
+
+/// Whether the hellos sent in both directions show an abbreviated handshake.
+pub(crate) fn is_resumed(
+    read: &[u8],
+    written: &[u8],
+    protocol_version: Option<ProtocolVersion>,
+) -> bool {
+    let messages: Vec<_> = plaintext_handshake_messages(read)
+        .into_iter()
+        .chain(plaintext_handshake_messages(written))
+        .collect();
+
+    let client_hello = messages.iter().find_map(|m| read_client_hello(m));
+    // A HelloRetryRequest is parsed as its own type, so this is the real ServerHello.
+    let server_hello = match messages.iter().find_map(|m| read_server_hello(m)) {
+        Some(server_hello) => server_hello,
+        None => return false,
+    };
+
+    match protocol_version {
+        Some(ProtocolVersion::TLSv1_3) => server_hello.get_psk_index().is_some(),
+        _ => match client_hello {
+            Some(client_hello) => {
+                !server_hello.session_id.is_empty()
+                    && server_hello.session_id == client_hello.session_id
+            }
+            None => false,
+        },
+    }
+}
@@ -0,0 +1,75 @@
+use rustls::{Certificate, CipherSuite, ClientSession, ProtocolVersion, ServerSession, Session};
+
+use crate::TlsStream;
+
+/// Session types `TlsStream` can describe with a `ConnectionInfo`.
+pub trait TlsSession: Session {
+    /// The SNI hostname the client asked for, only known on the server side.
+    fn sni_hostname(&self) -> Option<&str>;
+}
+
+impl TlsSession for ClientSession {
+    fn sni_hostname(&self) -> Option<&str> {
+        None
+    }
+}
+
+impl TlsSession for ServerSession {
+    fn sni_hostname(&self) -> Option<&str> {
+        self.get_sni_hostname()
+    }
+}
+
+/// Parameters negotiated by the handshake.
+#[derive(Debug, Clone)]
+pub struct ConnectionInfo {
+    pub protocol_version: Option<ProtocolVersion>,
+    pub cipher_suite: Option<CipherSuite>,
+    pub alpn_protocol: Option<Vec<u8>>,
+    pub sni_hostname: Option<String>,
+    pub peer_certificates: Option<Vec<Certificate>>,
+    pub resumed: bool,
+}
+
+impl ConnectionInfo {
+    /// The IANA name, e.g. `TLS_AES_128_GCM_SHA256`, where the `CipherSuite` variant is
+    /// `TLS13_AES_128_GCM_SHA256`.
+    pub fn cipher_suite_name(&self) -> Option<String> {
+        let name = match self.cipher_suite? {
+            CipherSuite::TLS13_AES_128_GCM_SHA256 => "TLS_AES_128_GCM_SHA256",
+            CipherSuite::TLS13_AES_256_GCM_SHA384 => "TLS_AES_256_GCM_SHA384",
+            CipherSuite::TLS13_CHACHA20_POLY1305_SHA256 => "TLS_CHACHA20_POLY1305_SHA256",
+            // TLS 1.2 variants are named as in the registry already.
+            suite => return Some(format!("{:?}", suite)),
+        };
+
+        Some(name.to_owned())
+    }
+}
+
+impl<S> TlsStream<ServerSession, S> {
+    /// The client's cert chain, leaf first, once verified by the client cert verifier.
+    pub fn client_certificates(&self) -> Option<Vec<Certificate>> {
+        self.inner.session.get_peer_certificates()
+    }
+}
+
+impl<SESS, S> TlsStream<SESS, S>
+where
+    SESS: TlsSession,
+{
+    pub fn connection_info(&self) -> ConnectionInfo {
+        let session = &self.inner.session;
+
+        ConnectionInfo {
+            protocol_version: session.get_protocol_version(),
+            cipher_suite: session
+                .get_negotiated_ciphersuite()
+                .map(|suite| suite.suite),
+            alpn_protocol: session.get_alpn_protocol().map(ToOwned::to_owned),
+            sni_hostname: session.sni_hostname().map(ToOwned::to_owned),
+            peer_certificates: session.get_peer_certificates(),
+            resumed: self.inner.resumed,
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_util::future::FusedFuture;
+use futures_util::io::{AsyncRead, AsyncWrite};
+use rustls::internal::msgs::enums::ContentType;
+use rustls::internal::msgs::handshake::{
+    ClientHelloPayload, ConvertProtocolNameList, ConvertServerNameList,
+};
+use rustls::{CipherSuite, ProtocolVersion, ServerConfig, ServerSession, SignatureScheme};
+
+use crate::acceptor::replay;
+use crate::error::Error;
+use crate::handshake::{Handshake, MidHandshake};
+use crate::hello::{plaintext_handshake_messages, read_client_hello, MAX_RECORD_LEN};
+use crate::timeout::{HandshakeTimeout, Timer};
+use crate::Accept;
+
+/// Far more than any real ClientHello needs.
+const MAX_CLIENT_HELLO_LEN: usize = 4 * MAX_RECORD_LEN;
+
+/// Reads the ClientHello first, so the `ServerConfig` can be picked from it, e.g. per
+/// tenant by SNI.
+#[derive(Clone, Default)]
+pub struct LazyAcceptor {
+    handshake_timeout: Option<HandshakeTimeout>,
+}
+
+impl LazyAcceptor {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Fails reading the ClientHello or the handshake after it with
+    /// `io::ErrorKind::TimedOut` when both aren't done before the future returned by
+    /// `timer(duration)` completes.
+    pub fn with_handshake_timeout<F, T>(mut self, duration: Duration, timer: F) -> Self
+    where
+        F: Fn(Duration) -> T + Send + Sync + 'static,
+        T: Future + Send + 'static,
+    {
+        self.handshake_timeout = Some(HandshakeTimeout::new(duration, timer));
+        self
+    }
+
+    /// The handshake deadline, if any, starts when this is called.
+    pub fn accept<S>(&self, stream: S) -> ReadClientHello<S>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        ReadClientHello {
+            inner: Some(stream),
+            buffered: vec![],
+            timer: self.handshake_timeout.as_ref().map(HandshakeTimeout::timer),
+        }
+    }
+}
+
+/// The parts of a ClientHello useful for picking a `ServerConfig`.
+#[derive(Debug, Clone)]
+pub struct ClientHello {
+    pub server_name: Option<String>,
+    pub alpn_protocols: Vec<Vec<u8>>,
+    pub cipher_suites: Vec<CipherSuite>,
+    pub signature_schemes: Vec<SignatureScheme>,
+    /// From the supported_versions extension, else the legacy version field.
+    pub protocol_versions: Vec<ProtocolVersion>,
+}
+
+impl From<&ClientHelloPayload> for ClientHello {
+    fn from(payload: &ClientHelloPayload) -> Self {
+        ClientHello {
+            server_name: payload
+                .get_sni_extension()
+                .and_then(|names| names.get_single_hostname())
+                .map(|name| <&str>::from(name).to_owned()),
+            alpn_protocols: payload
+                .get_alpn_extension()
+                .map(|protocols| protocols.to_slices().iter().map(|p| p.to_vec()).collect())
+                .unwrap_or_default(),
+            cipher_suites: payload.cipher_suites.clone(),
+            signature_schemes: payload.get_sigalgs_extension().cloned().unwrap_or_default(),
+            protocol_versions: payload
+                .get_versions_extension()
+                .cloned()
+                .unwrap_or_else(|| vec![payload.client_version]),
+        }
+    }
+}
+
+/// Future returned by `LazyAcceptor::accept`.
+pub struct ReadClientHello<S> {
+    inner: Option<S>,
+    buffered: Vec<u8>,
+    timer: Option<Timer>,
+}
+
+/// `None` until the ClientHello is complete.
+fn parse_client_hello(buffered: &[u8]) -> io::Result<Option<ClientHello>> {
+    if matches!(buffered.first(), Some(&typ) if typ != ContentType::Handshake.get_u8()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a tls handshake",
+        ));
+    }
+
+    match plaintext_handshake_messages(buffered).first() {
+        Some(message) => read_client_hello(message)
+            .map(|payload| Some(ClientHello::from(&payload)))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid client hello")),
+        None if buffered.len() > MAX_CLIENT_HELLO_LEN => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "client hello too large",
+        )),
+        None => Ok(None),
+    }
+}
+
+impl<S> Future for ReadClientHello<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    type Output = io::Result<StartHandshake<S>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        let stream = match this.inner.as_mut() {
+            Some(stream) => stream,
+            None => {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::NotConnected,
+                    "client hello polled after completion",
+                )))
+            }
+        };
+
+        let ret = loop {
+            let mut buf = [0; 4096];
+            match Pin::new(&mut *stream).poll_read(cx, &mut buf) {
+                Poll::Ready(Ok(0)) => {
+                    break Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "tls handshake eof",
+                    ))
+                }
+                Poll::Ready(Ok(n)) => this.buffered.extend_from_slice(&buf[..n]),
+                Poll::Ready(Err(err)) => break Err(err),
+                Poll::Pending => {
+                    if let Some(timer) = this.timer.as_mut() {
+                        if timer.as_mut().poll(cx).is_ready() {
+                            break Err(Error::HandshakeTimeout.into());
+                        }
+                    }
+                    return Poll::Pending;
+                }
+            }
+
+            match parse_client_hello(&this.buffered) {
+                Ok(Some(client_hello)) => break Ok(client_hello),
+                Ok(None) => {}
+                Err(err) => break Err(err),
+            }
+        };
+
+        let stream = this.inner.take().expect("never");
+
+        Poll::Ready(ret.map(|client_hello| StartHandshake {
+            client_hello,
+            buffered: std::mem::take(&mut this.buffered),
+            stream,
+            timer: this.timer.take(),
+        }))
+    }
+}
+
+impl<S> FusedFuture for ReadClientHello<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn is_terminated(&self) -> bool {
+        self.inner.is_none()
+    }
+}
+
+/// A connection whose ClientHello has been read, but not answered.
+pub struct StartHandshake<S> {
+    client_hello: ClientHello,
+    buffered: Vec<u8>,
+    stream: S,
+    timer: Option<Timer>,
+}
+
+impl<S> StartHandshake<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    pub fn client_hello(&self) -> &ClientHello {
+        &self.client_hello
+    }
+
+    /// Finishes the handshake with `config`, replaying the bytes read so far.
+    pub fn into_accept(self, config: Arc<ServerConfig>) -> Accept<S> {
+        let mut session = ServerSession::new(&config);
+        if let Err(err) = replay(&mut session, &self.buffered) {
+            return Accept(MidHandshake::Error(err));
+        }
+
+        Accept(MidHandshake::Handshaking(
+            Handshake::new(session, self.stream)
+                .replayed(&self.buffered)
+                .with_timer(self.timer),
+        ))
+    }
+
+    /// The transport and the bytes read from it, e.g. to reject the connection.
+    pub fn into_inner(self) -> (S, Vec<u8>) {
+        (self.stream, self.buffered)
+    }
+}
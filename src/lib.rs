@@ -1,26 +1,107 @@
-use std::future::Future;
-use std::io::{self, Read, Write};
+use std::io;
 use std::pin::Pin;
-use std::task::{Context, Poll};
+use std::task::{Context, Poll, Waker};
 
-use futures_io_traits_sync_wrapper::Wrapper as AsyncRWSyncWrapper;
+use async_stream_packed::SyncableWithContextAsyncStream;
+use futures_util::future::poll_fn;
 use futures_util::io::{AsyncRead, AsyncWrite};
-use rustls::{ClientSession, ServerSession, Session, Stream};
+use futures_util::ready;
+use rustls::Session;
 
 #[cfg(feature = "acceptor")]
 mod acceptor;
 #[cfg(feature = "acceptor")]
-pub use acceptor::TlsAcceptor;
+pub use acceptor::{Accept, TlsAcceptor, TlsAcceptorBuilder};
+
+#[cfg(feature = "acceptor")]
+mod reload;
+#[cfg(feature = "acceptor")]
+pub use reload::PemFileWatcher;
+
+#[cfg(feature = "acceptor")]
+mod sni;
+#[cfg(feature = "acceptor")]
+pub use sni::SniCertResolver;
 
 #[cfg(feature = "connector")]
 mod connector;
 #[cfg(feature = "connector")]
-pub use connector::TlsConnector;
+pub use connector::{Connect, TlsConnector, TlsConnectorBuilder};
+
+mod error;
+pub use error::Error;
+
+mod handshake;
+pub use handshake::{client_handshake, server_handshake, HandshakeError};
+
+mod hello;
+
+#[cfg(feature = "acceptor")]
+mod lazy;
+#[cfg(feature = "acceptor")]
+pub use lazy::{ClientHello, LazyAcceptor, ReadClientHello, StartHandshake};
+
+mod info;
+
+#[cfg(feature = "native-roots")]
+mod native_roots;
+
+#[cfg(any(feature = "acceptor", feature = "connector"))]
+mod pem;
+
+#[cfg(feature = "proxy-protocol")]
+mod proxy_protocol;
+#[cfg(feature = "proxy-protocol")]
+pub use proxy_protocol::{read_proxy_header, ProxyHeader};
+
+#[cfg(feature = "connector")]
+mod server_name;
+#[cfg(feature = "connector")]
+pub use server_name::{IntoServerName, ServerName};
+
+#[cfg(feature = "connector")]
+mod pinning;
+pub use info::{ConnectionInfo, TlsSession};
+
+#[cfg(any(feature = "acceptor", feature = "connector"))]
+mod session_store;
+#[cfg(feature = "connector")]
+pub use session_store::LruClientSessionStore;
+#[cfg(feature = "acceptor")]
+pub use session_store::LruServerSessionStore;
+
+#[cfg(feature = "acceptor")]
+mod sniff;
+#[cfg(feature = "acceptor")]
+pub use sniff::{MaybeTls, PrefixedStream};
+
+mod split;
+pub use split::{ReadHalf, ReuniteError, WriteHalf};
+
+/// The plaintext negotiation of protocols that upgrade a connection to TLS mid-stream.
+/// Everything is read byte by byte, so plaintext sent after the upgrade command is never
+/// taken for data sent over TLS.
+#[cfg(any(feature = "acceptor", feature = "connector"))]
+pub mod starttls;
+
+#[cfg(feature = "acceptor")]
+mod ticketer;
+#[cfg(feature = "acceptor")]
+pub use ticketer::RotatingTicketer;
+
+#[cfg(any(feature = "acceptor", feature = "connector"))]
+mod timeout;
+
+#[cfg(feature = "connector")]
+mod verify;
 
 pub mod prelude {
     pub use rustls::{
-        internal::pemfile, ClientConfig, ClientSession, NoClientAuth, ServerConfig, ServerSession,
-        Session as RustlsSession,
+        internal::msgs::enums::AlertDescription, internal::pemfile, sign::CertifiedKey,
+        AllowAnyAnonymousOrAuthenticatedClient, AllowAnyAuthenticatedClient, Certificate,
+        CipherSuite, ClientConfig, ClientSession, NoClientAuth, PrivateKey, ProducesTickets,
+        ProtocolVersion, RootCertStore, ServerConfig, ServerSession, Session as RustlsSession,
+        SignatureScheme, StoresClientSessions, StoresServerSessions, TLSError,
     };
 
     #[cfg(feature = "connector")]
@@ -36,9 +117,77 @@ pub struct TlsStream<SESS, S> {
 struct TlsStreamInner<SESS, S> {
     session: SESS,
     stream: S,
+    state: TlsState,
+    truncation_policy: TruncationPolicy,
+    eof: Option<Eof>,
+    resumed: bool,
+    /// The task last polling a `WriteHalf`, woken when reading queues something to send.
+    write_waker: Option<Waker>,
+}
+
+/// What `poll_read` does when the transport hits EOF before the peer's close_notify.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum TruncationPolicy {
+    /// Fail the read with `io::ErrorKind::UnexpectedEof`.
+    Strict,
+    /// Report it as a normal EOF.
+    #[default]
+    Lenient,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Eof {
+    CloseNotify,
+    Truncated,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum TlsState {
+    Stream,
+    ReadShutdown,
+    WriteShutdown,
+    FullyShutdown,
+}
+
+impl TlsState {
+    fn shutdown_read(&mut self) {
+        *self = match *self {
+            TlsState::WriteShutdown | TlsState::FullyShutdown => TlsState::FullyShutdown,
+            _ => TlsState::ReadShutdown,
+        };
+    }
+
+    fn shutdown_write(&mut self) {
+        *self = match *self {
+            TlsState::ReadShutdown | TlsState::FullyShutdown => TlsState::FullyShutdown,
+            _ => TlsState::WriteShutdown,
+        };
+    }
+
+    fn readable(self) -> bool {
+        matches!(self, TlsState::Stream | TlsState::WriteShutdown)
+    }
+
+    fn writeable(self) -> bool {
+        matches!(self, TlsState::Stream | TlsState::ReadShutdown)
+    }
 }
 
 impl<SESS, S> TlsStream<SESS, S> {
+    pub(crate) fn new(session: SESS, stream: S) -> Self {
+        TlsStream {
+            inner: TlsStreamInner {
+                session,
+                stream,
+                state: TlsState::Stream,
+                truncation_policy: Default::default(),
+                eof: None,
+                resumed: false,
+                write_waker: None,
+            },
+        }
+    }
+
     pub fn get_mut(&mut self) -> &mut S {
         &mut self.inner.stream
     }
@@ -54,64 +203,195 @@ impl<SESS, S> TlsStream<SESS, S> {
     pub fn get_session_ref(&self) -> &SESS {
         &self.inner.session
     }
-}
 
-pub async fn client_handshake<S>(
-    session: ClientSession,
-    stream: S,
-) -> io::Result<TlsStream<ClientSession, S>>
-where
-    S: AsyncRead + AsyncWrite + Unpin,
-{
-    handshake(session, stream).await
-}
+    pub fn is_read_shutdown(&self) -> bool {
+        !self.inner.state.readable()
+    }
 
-pub async fn server_handshake<S>(
-    session: ServerSession,
-    stream: S,
-) -> io::Result<TlsStream<ServerSession, S>>
-where
-    S: AsyncRead + AsyncWrite + Unpin,
-{
-    handshake(session, stream).await
+    pub fn is_write_shutdown(&self) -> bool {
+        !self.inner.state.writeable()
+    }
+
+    pub fn truncation_policy(&self) -> TruncationPolicy {
+        self.inner.truncation_policy
+    }
+
+    pub fn set_truncation_policy(&mut self, policy: TruncationPolicy) {
+        self.inner.truncation_policy = policy;
+    }
+
+    /// How the read side ended, `None` while it is still open.
+    pub fn eof(&self) -> Option<Eof> {
+        self.inner.eof
+    }
 }
 
-async fn handshake<SESS, S>(session: SESS, stream: S) -> io::Result<TlsStream<SESS, S>>
+impl<SESS, S> TlsStream<SESS, S>
 where
     SESS: Session + Unpin,
     S: AsyncRead + AsyncWrite + Unpin,
 {
-    Handshake(Some((session, stream))).await
-}
+    /// Sends close_notify and flushes it, but keeps the transport open so the peer's
+    /// remaining data can still be read.
+    pub async fn shutdown_write(&mut self) -> io::Result<()> {
+        poll_fn(|cx| self.inner.poll_shutdown_write(cx)).await
+    }
 
-struct Handshake<SESS, S>(Option<(SESS, S)>);
+    pub fn poll_shutdown_write(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        self.get_mut().inner.poll_shutdown_write(cx)
+    }
+}
 
-impl<SESS, S> Future for Handshake<SESS, S>
+impl<SESS, S> TlsStreamInner<SESS, S>
 where
     SESS: Session + Unpin,
     S: AsyncRead + AsyncWrite + Unpin,
 {
-    type Output = io::Result<TlsStream<SESS, S>>;
+    fn poll_write_tls(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        let mut sync_stream = SyncableWithContextAsyncStream::new(&mut self.stream, cx);
+
+        while self.session.wants_write() {
+            match self.session.write_tls(&mut sync_stream) {
+                Ok(0) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Ok(_) => {}
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => return Poll::Pending,
+                Err(err) => return Poll::Ready(Err(err)),
+            }
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_read_eof(&mut self) -> Poll<io::Result<usize>> {
+        match (self.eof, self.truncation_policy) {
+            (Some(Eof::Truncated), TruncationPolicy::Strict) => Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "peer closed connection without sending close_notify",
+            ))),
+            _ => Poll::Ready(Ok(0)),
+        }
+    }
+
+    /// With `register_write`, a TLS write that processing queued (e.g. an alert reply)
+    /// and that can't go out yet wakes `cx` once the transport is writable. A
+    /// `ReadHalf` passes false, it never writes to the transport and leaves the queued
+    /// write to its `WriteHalf`, waking the task that last polled it.
+    fn poll_read(
+        &mut self,
+        cx: &mut Context,
+        buf: &mut [u8],
+        register_write: bool,
+    ) -> Poll<io::Result<usize>> {
+        if !self.state.readable() {
+            return self.poll_read_eof();
+        }
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
-        let this = self.get_mut();
+        loop {
+            match self.session.read(buf) {
+                Ok(0) if !buf.is_empty() => {}
+                Ok(n) => return Poll::Ready(Ok(n)),
+                // rustls reports a received close_notify this way once all plaintext is consumed.
+                Err(ref err) if err.kind() == io::ErrorKind::ConnectionAborted => {
+                    self.state.shutdown_read();
+                    self.eof = Some(Eof::CloseNotify);
+                    return self.poll_read_eof();
+                }
+                Err(err) => return Poll::Ready(Err(err)),
+            }
 
-        let (mut session, mut stream) = this.0.take().expect("never");
+            let wanted_write = self.session.wants_write();
+            let mut sync_stream = SyncableWithContextAsyncStream::new(&mut self.stream, cx);
+
+            match self.session.read_tls(&mut sync_stream) {
+                Ok(0) => {
+                    self.state.shutdown_read();
+                    self.eof = Some(Eof::Truncated);
+                    return self.poll_read_eof();
+                }
+                Ok(_) => {}
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => return Poll::Pending,
+                Err(err) => return Poll::Ready(Err(err)),
+            }
 
-        let mut sync_stream = AsyncRWSyncWrapper::new(&mut stream, cx);
+            if let Err(err) = self.session.process_new_packets() {
+                if register_write {
+                    // Try a last-gasp write of the alert describing this error.
+                    let _ = self.session.write_tls(&mut sync_stream);
+                } else if !wanted_write {
+                    self.wake_writer();
+                }
 
-        match session.complete_io(&mut sync_stream) {
-            Ok(_) => Poll::Ready(Ok(TlsStream {
-                inner: TlsStreamInner { session, stream },
-            })),
-            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {
-                this.0 = Some((session, stream));
+                return Poll::Ready(Err(Error::from(err).into()));
+            }
 
-                Poll::Pending
+            if register_write {
+                // Push out anything processing queued. If it's pending, keep reading, `cx`
+                // is woken by whichever of the two comes first.
+                if let Poll::Ready(Err(err)) = self.poll_write_tls(cx) {
+                    return Poll::Ready(Err(err));
+                }
+            } else if !wanted_write && self.session.wants_write() {
+                // A write that was already pending has the writer waiting on the transport.
+                self.wake_writer();
             }
-            Err(err) => Poll::Ready(Err(err)),
         }
     }
+
+    fn register_writer(&mut self, cx: &Context) {
+        match self.write_waker {
+            Some(ref waker) if waker.will_wake(cx.waker()) => {}
+            _ => self.write_waker = Some(cx.waker().clone()),
+        }
+    }
+
+    fn wake_writer(&mut self) {
+        if let Some(waker) = self.write_waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn poll_write(&mut self, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        if !self.state.writeable() {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "tls stream write side is shutdown",
+            )));
+        }
+
+        ready!(self.poll_write_tls(cx))?;
+
+        let n = self.session.write(buf)?;
+
+        // Errors are reported on the next call, the bytes are already consumed.
+        let _ = self.poll_write_tls(cx);
+
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        self.session.flush()?;
+
+        ready!(self.poll_write_tls(cx))?;
+
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown_write(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        if self.state.writeable() {
+            self.session.send_close_notify();
+            self.state.shutdown_write();
+        }
+
+        ready!(self.poll_write_tls(cx))?;
+
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_close(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        ready!(self.poll_shutdown_write(cx))?;
+
+        Pin::new(&mut self.stream).poll_close(cx)
+    }
 }
 
 impl<SESS, S> AsyncRead for TlsStream<SESS, S>
@@ -124,17 +404,7 @@ where
         cx: &mut Context,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
-        let this = self.get_mut();
-
-        let mut sync_stream = AsyncRWSyncWrapper::new(&mut this.inner.stream, cx);
-
-        let mut rustls_stream = Stream::new(&mut this.inner.session, &mut sync_stream);
-
-        match rustls_stream.read(buf) {
-            Ok(n) => Poll::Ready(Ok(n)),
-            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
-            Err(err) => Poll::Ready(Err(err)),
-        }
+        self.get_mut().inner.poll_read(cx, buf, true)
     }
 }
 
@@ -144,34 +414,14 @@ where
     S: AsyncRead + AsyncWrite + Unpin,
 {
     fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
-        let this = self.get_mut();
-
-        let mut sync_stream = AsyncRWSyncWrapper::new(&mut this.inner.stream, cx);
-
-        let mut rustls_stream = Stream::new(&mut this.inner.session, &mut sync_stream);
-
-        match rustls_stream.write(buf) {
-            Ok(n) => Poll::Ready(Ok(n)),
-            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => Poll::Pending,
-            Err(err) => Poll::Ready(Err(err)),
-        }
+        self.get_mut().inner.poll_write(cx, buf)
     }
 
     fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
-        let this = self.get_mut();
-
-        let mut sync_stream = AsyncRWSyncWrapper::new(&mut this.inner.stream, cx);
-
-        let mut rustls_stream = Stream::new(&mut this.inner.session, &mut sync_stream);
-
-        rustls_stream.flush()?;
-
-        Pin::new(&mut this.inner.stream).poll_flush(cx)
+        self.get_mut().inner.poll_flush(cx)
     }
 
     fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
-        let this = self.get_mut();
-
-        Pin::new(&mut this.inner.stream).poll_close(cx)
+        self.get_mut().inner.poll_close(cx)
     }
 }
@@ -0,0 +1,101 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use rustls::{Certificate, RootCertStore};
+
+use crate::pem::load_certs;
+
+const CERT_FILES: &[&str] = &[
+    "/etc/ssl/certs/ca-certificates.crt",
+    "/etc/pki/tls/certs/ca-bundle.crt",
+    "/etc/ssl/ca-bundle.pem",
+    "/etc/ssl/cert.pem",
+];
+const CERT_DIRS: &[&str] = &["/etc/ssl/certs"];
+
+/// Adds the OS trust store, like OpenSSL `SSL_CERT_FILE` and `SSL_CERT_DIR` override
+/// the default locations. Unreadable files and unparsable certs are skipped and logged,
+/// it fails only when no root cert is left at all.
+pub(crate) fn add_native_roots(root_store: &mut RootCertStore) -> io::Result<usize> {
+    let cert_file = env::var_os("SSL_CERT_FILE").map(PathBuf::from);
+    let cert_dirs = env::var_os("SSL_CERT_DIR").map(|dirs| env::split_paths(&dirs).collect());
+
+    let (files, dirs): (Vec<PathBuf>, Vec<PathBuf>) = match (cert_file, cert_dirs) {
+        (None, None) => (
+            CERT_FILES
+                .iter()
+                .map(PathBuf::from)
+                .filter(|path| path.is_file())
+                .take(1)
+                .collect(),
+            CERT_DIRS
+                .iter()
+                .map(PathBuf::from)
+                .filter(|path| path.is_dir())
+                .collect(),
+        ),
+        (cert_file, cert_dirs) => (
+            cert_file.into_iter().collect(),
+            cert_dirs.unwrap_or_default(),
+        ),
+    };
+
+    let mut certs = vec![];
+    for path in &files {
+        certs.extend(load_certs_or_skip(path));
+    }
+    for path in &dirs {
+        certs.extend(load_certs_from_dir(path));
+    }
+
+    let n = certs
+        .iter()
+        .filter(|cert| root_store.add(cert).is_ok())
+        .count();
+    if n == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "no native root cert found",
+        ));
+    }
+
+    Ok(n)
+}
+
+/// For when the OS trust store is only used by default, a host without one still gets
+/// a working connector.
+pub(crate) fn add_native_roots_or_warn(root_store: &mut RootCertStore) {
+    if let Err(err) = add_native_roots(root_store) {
+        log::warn!("not using native root certs: {}", err);
+    }
+}
+
+/// Unlike `pem::load_certs_from_dir`, reads every file, as they are usually named
+/// by hash, e.g. `/etc/ssl/certs/002c0b4f.0`.
+fn load_certs_from_dir(path: &Path) -> Vec<Certificate> {
+    let mut paths: Vec<_> = match fs::read_dir(path) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file())
+            .collect(),
+        Err(err) => {
+            log::warn!("skipping native root certs in {}: {}", path.display(), err);
+            return vec![];
+        }
+    };
+    paths.sort();
+
+    paths
+        .iter()
+        .flat_map(|path| load_certs_or_skip(path))
+        .collect()
+}
+
+fn load_certs_or_skip(path: &Path) -> Vec<Certificate> {
+    load_certs(path).unwrap_or_else(|err| {
+        log::warn!("skipping native root certs: {}", err);
+        vec![]
+    })
+}
@@ -0,0 +1,214 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rustls::{sign, Certificate, PrivateKey};
+
+pub(crate) fn load_certs(path: &Path) -> io::Result<Vec<Certificate>> {
+    let certs: Vec<_> = read_sections(path)?
+        .into_iter()
+        .filter(|(label, _)| label == "CERTIFICATE")
+        .map(|(_, der)| Certificate(der))
+        .collect();
+    if certs.is_empty() {
+        return Err(invalid_input(path, "no CERTIFICATE found"));
+    }
+
+    Ok(certs)
+}
+
+/// Certificates from every `*.pem` and `*.crt` file in `path`.
+#[cfg_attr(not(feature = "connector"), allow(dead_code))]
+pub(crate) fn load_certs_from_dir(path: &Path) -> io::Result<Vec<Certificate>> {
+    let mut paths = vec![];
+    for entry in fs::read_dir(path)
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))?
+    {
+        let path = entry?.path();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("pem") | Some("crt") if path.is_file() => paths.push(path),
+            _ => {}
+        }
+    }
+    paths.sort();
+
+    let mut certs = vec![];
+    for path in paths {
+        certs.extend(load_certs(&path)?);
+    }
+    if certs.is_empty() {
+        return Err(invalid_input(path, "no CERTIFICATE found"));
+    }
+
+    Ok(certs)
+}
+
+/// Accepts PKCS#8 (`PRIVATE KEY`), PKCS#1 (`RSA PRIVATE KEY`) and SEC1 (`EC PRIVATE KEY`).
+pub(crate) fn load_private_key(path: &Path) -> io::Result<PrivateKey> {
+    let key = read_sections(path)?
+        .into_iter()
+        .find_map(|(label, der)| match label.as_str() {
+            "PRIVATE KEY" | "RSA PRIVATE KEY" => Some(Ok(PrivateKey(der))),
+            "EC PRIVATE KEY" => Some(
+                sec1_to_pkcs8(&der)
+                    .map(PrivateKey)
+                    .ok_or_else(|| invalid_input(path, "unsupported EC PRIVATE KEY curve")),
+            ),
+            "ENCRYPTED PRIVATE KEY" => Some(Err(invalid_input(
+                path,
+                "encrypted private keys are not supported",
+            ))),
+            _ => None,
+        })
+        .unwrap_or_else(|| {
+            Err(invalid_input(
+                path,
+                "no PRIVATE KEY, RSA PRIVATE KEY or EC PRIVATE KEY found",
+            ))
+        })?;
+
+    sign::any_supported_type(&key)
+        .map_err(|_| invalid_input(path, "unsupported or malformed private key"))?;
+
+    Ok(key)
+}
+
+fn read_sections(path: &Path) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))?;
+
+    let mut sections = vec![];
+    let mut section: Option<(&str, String)> = None;
+    for line in content.lines().map(str::trim) {
+        if let Some(label) = line
+            .strip_prefix("-----BEGIN ")
+            .and_then(|line| line.strip_suffix("-----"))
+        {
+            section = Some((label, String::new()));
+        } else if let Some(label) = line
+            .strip_prefix("-----END ")
+            .and_then(|line| line.strip_suffix("-----"))
+        {
+            match section.take() {
+                Some((begin_label, b64)) if begin_label == label => {
+                    let der = base64::decode(&b64).map_err(|_| {
+                        invalid_input(path, &format!("invalid base64 in {}", label))
+                    })?;
+                    sections.push((label.to_owned(), der));
+                }
+                _ => return Err(invalid_input(path, &format!("unexpected END {}", label))),
+            }
+        } else if let Some((_, b64)) = section.as_mut() {
+            b64.push_str(line);
+        }
+    }
+    if let Some((label, _)) = section {
+        return Err(invalid_input(path, &format!("missing END {}", label)));
+    }
+
+    Ok(sections)
+}
+
+pub(crate) fn invalid_input(path: &Path, msg: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{}: {}", path.display(), msg),
+    )
+}
+
+const OID_EC_PUBLIC_KEY: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+const OID_P256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+const OID_P384: &[u8] = &[0x2b, 0x81, 0x04, 0x00, 0x22];
+
+/// Wraps an RFC 5915 ECPrivateKey, which must name its curve, into a PKCS#8 PrivateKeyInfo.
+fn sec1_to_pkcs8(sec1: &[u8]) -> Option<Vec<u8>> {
+    let (tag, mut body, _) = der::read(sec1)?;
+    if tag != der::SEQUENCE {
+        return None;
+    }
+
+    let mut curve = None;
+    while !body.is_empty() {
+        let (tag, value, rest) = der::read(body)?;
+        if tag == der::CONTEXT_0 {
+            let (tag, oid, _) = der::read(value)?;
+            if tag == der::OID {
+                curve = Some(oid);
+            }
+        }
+        body = rest;
+    }
+    let curve = curve.filter(|oid| *oid == OID_P256 || *oid == OID_P384)?;
+
+    let algorithm = der::write(
+        der::SEQUENCE,
+        &[
+            der::write(der::OID, OID_EC_PUBLIC_KEY),
+            der::write(der::OID, curve),
+        ]
+        .concat(),
+    );
+
+    Some(der::write(
+        der::SEQUENCE,
+        &[
+            der::write(der::INTEGER, &[0]),
+            algorithm,
+            der::write(der::OCTET_STRING, sec1),
+        ]
+        .concat(),
+    ))
+}
+
+/// Just enough DER for the key formats above.
+pub(crate) mod der {
+    pub(crate) const INTEGER: u8 = 0x02;
+    pub(crate) const OCTET_STRING: u8 = 0x04;
+    pub(crate) const OID: u8 = 0x06;
+    pub(crate) const SEQUENCE: u8 = 0x30;
+    pub(crate) const CONTEXT_0: u8 = 0xa0;
+
+    /// Returns the tag, the value and the rest.
+    pub(crate) fn read(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+        let tag = *input.first()?;
+        let first = *input.get(1)? as usize;
+
+        let (len, header_len) = if first < 0x80 {
+            (first, 2)
+        } else {
+            let n = first & 0x7f;
+            if n == 0 || n > 4 {
+                return None;
+            }
+            let len = input
+                .get(2..2 + n)?
+                .iter()
+                .fold(0, |len, b| (len << 8) | *b as usize);
+            (len, 2 + n)
+        };
+
+        let value = input.get(header_len..header_len.checked_add(len)?)?;
+        Some((tag, value, &input[header_len + len..]))
+    }
+
+    pub(crate) fn write(tag: u8, value: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag];
+
+        let len = value.len();
+        if len < 0x80 {
+            out.push(len as u8);
+        } else {
+            let bytes: Vec<u8> = len
+                .to_be_bytes()
+                .iter()
+                .copied()
+                .skip_while(|b| *b == 0)
+                .collect();
+            out.push(0x80 | bytes.len() as u8);
+            out.extend_from_slice(&bytes);
+        }
+
+        out.extend_from_slice(value);
+        out
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashSet;
+use std::io;
+use std::sync::Arc;
+
+use ring::digest::{digest, SHA256};
+use rustls::internal::msgs::handshake::DigitallySignedStruct;
+use rustls::{
+    Certificate, HandshakeSignatureValid, RootCertStore, ServerCertVerified, ServerCertVerifier,
+    SignatureScheme, TLSError,
+};
+use webpki::trust_anchor_util::cert_der_as_trust_anchor;
+use webpki::{DNSNameRef, TrustAnchor};
+
+use crate::error::PIN_MISMATCH;
+use crate::pem::der;
+use crate::verify::{trust_anchors, verify_chain};
+
+pub(crate) fn parse_pin(pin: &str) -> io::Result<[u8; 32]> {
+    let bytes = base64::decode(pin).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid SPKI pin {:?}: not base64", pin),
+        )
+    })?;
+    if bytes.len() != 32 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid SPKI pin {:?}: not a SHA-256 hash", pin),
+        ));
+    }
+
+    let mut hash = [0; 32];
+    hash.copy_from_slice(&bytes);
+    Ok(hash)
+}
+
+/// Runs `inner` first, then requires the end-entity cert, an intermediate or a root on
+/// the verified path to have a pinned SPKI SHA-256. Other certs the server sends
+/// aren't necessarily on that path, anyone can append them.
+pub(crate) struct PinningVerifier {
+    pub(crate) inner: Arc<dyn ServerCertVerifier>,
+    pub(crate) pins: HashSet<[u8; 32]>,
+}
+
+impl PinningVerifier {
+    fn is_pinned(&self, cert: &Certificate) -> bool {
+        match spki_sha256(cert) {
+            Some(hash) => self.pins.contains(&hash),
+            None => false,
+        }
+    }
+
+    /// Whether the chain verifies to a pinned root, or to a pinned intermediate taken as
+    /// the trust anchor, which only holds if that intermediate issued the path.
+    fn verifies_to_pinned_ca(
+        &self,
+        roots: &RootCertStore,
+        presented_certs: &[Certificate],
+    ) -> bool {
+        let mut pinned_cas: Vec<TrustAnchor> = trust_anchors(roots)
+            .into_iter()
+            .filter(|root| {
+                // The anchor's SPKI is without the SEQUENCE header.
+                let spki = der::write(der::SEQUENCE, root.spki);
+                self.pins.contains(&sha256(&spki))
+            })
+            .collect();
+        pinned_cas.extend(
+            presented_certs
+                .iter()
+                .skip(1)
+                .filter(|cert| self.is_pinned(cert))
+                .filter_map(|cert| cert_der_as_trust_anchor(&cert.0).ok()),
+        );
+
+        !pinned_cas.is_empty() && verify_chain(&pinned_cas, presented_certs).is_ok()
+    }
+}
+
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        roots: &RootCertStore,
+        presented_certs: &[Certificate],
+        dns_name: DNSNameRef,
+        ocsp_response: &[u8],
+    ) -> Result<ServerCertVerified, TLSError> {
+        let verified =
+            self.inner
+                .verify_server_cert(roots, presented_certs, dns_name, ocsp_response)?;
+
+        let end_entity_pinned = match presented_certs.first() {
+            Some(end_entity) => self.is_pinned(end_entity),
+            None => false,
+        };
+        if end_entity_pinned || self.verifies_to_pinned_ca(roots, presented_certs) {
+            Ok(verified)
+        } else {
+            Err(TLSError::General(PIN_MISMATCH.to_owned()))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &Certificate,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, TLSError> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &Certificate,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, TLSError> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// SHA-256 of the DER SubjectPublicKeyInfo, the value of an HPKP `pin-sha256`.
+fn spki_sha256(cert: &Certificate) -> Option<[u8; 32]> {
+    let (_, certificate, _) = der::read(&cert.0)?;
+    let (_, mut tbs_certificate, _) = der::read(certificate)?;
+
+    // version (optional), serialNumber, signature, issuer, validity, subject
+    if tbs_certificate.first() == Some(&der::CONTEXT_0) {
+        tbs_certificate = der::read(tbs_certificate)?.2;
+    }
+    for _ in 0..5 {
+        tbs_certificate = der::read(tbs_certificate)?.2;
+    }
+
+    let (_, _, rest) = der::read(tbs_certificate)?;
+    Some(sha256(
+        &tbs_certificate[..tbs_certificate.len() - rest.len()],
+    ))
+}
+
+fn sha256(bytes: &[u8]) -> [u8; 32] {
+    let mut hash = [0; 32];
+    hash.copy_from_slice(digest(&SHA256, bytes).as_ref());
+    hash
+}
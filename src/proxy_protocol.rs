@@ -0,0 +1,185 @@
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str;
+
+use futures_util::io::{AsyncRead, AsyncReadExt};
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+/// Including the CRLF.
+const V1_MAX_LEN: usize = 107;
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+const V2_HEADER_LEN: usize = 16;
+
+/// A PROXY protocol header, as sent by a load balancer before the client's bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyHeader {
+    /// 1 for the text format, 2 for the binary one.
+    pub version: u8,
+    /// `None` for `UNKNOWN` (v1), `LOCAL` or non-IP addresses (v2).
+    pub source: Option<SocketAddr>,
+    pub destination: Option<SocketAddr>,
+    /// The v2 type-length-values, `(type, value)`.
+    pub tlvs: Vec<(u8, Vec<u8>)>,
+}
+
+impl ProxyHeader {
+    /// The value of the first TLV of type `typ`, e.g. `0x01` for ALPN or `0x02` for the
+    /// authority.
+    pub fn tlv(&self, typ: u8) -> Option<&[u8]> {
+        self.tlvs
+            .iter()
+            .find(|(t, _)| *t == typ)
+            .map(|(_, value)| value.as_slice())
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid proxy protocol header: {}", msg),
+    )
+}
+
+/// Reads a v1 or v2 header, and not a byte more, from the start of `stream`.
+pub async fn read_proxy_header<S>(stream: &mut S) -> io::Result<ProxyHeader>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut header = vec![0; V1_PREFIX.len()];
+    stream.read_exact(&mut header).await?;
+
+    if header == V1_PREFIX {
+        // Byte by byte, the client's bytes follow right after the CRLF.
+        while !header.ends_with(b"\r\n") {
+            if header.len() == V1_MAX_LEN {
+                return Err(invalid("v1 header too long"));
+            }
+            let mut byte = [0; 1];
+            stream.read_exact(&mut byte).await?;
+            header.push(byte[0]);
+        }
+
+        parse_v1(&header[V1_PREFIX.len()..header.len() - 2])
+    } else if V2_SIGNATURE.starts_with(&header) {
+        header.resize(V2_HEADER_LEN, 0);
+        stream.read_exact(&mut header[V1_PREFIX.len()..]).await?;
+        if &header[..V2_SIGNATURE.len()] != V2_SIGNATURE {
+            return Err(invalid("bad v2 signature"));
+        }
+
+        let len = u16::from_be_bytes([header[14], header[15]]) as usize;
+        let mut body = vec![0; len];
+        stream.read_exact(&mut body).await?;
+
+        parse_v2(header[12], header[13], &body)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "missing proxy protocol header",
+        ))
+    }
+}
+
+/// `line` is what follows `PROXY `, without the CRLF.
+fn parse_v1(line: &[u8]) -> io::Result<ProxyHeader> {
+    let line = str::from_utf8(line).map_err(|_| invalid("v1 header isn't ascii"))?;
+    let fields: Vec<&str> = line.split(' ').collect();
+
+    let (source, destination) = match fields.as_slice() {
+        ["UNKNOWN", ..] => (None, None),
+        [protocol @ ("TCP4" | "TCP6"), source, destination, source_port, destination_port] => {
+            let ip = |field: &str| {
+                let ip: IpAddr = field.parse().map_err(|_| invalid("bad v1 address"))?;
+                match (ip, *protocol) {
+                    (IpAddr::V4(_), "TCP4") | (IpAddr::V6(_), "TCP6") => Ok(ip),
+                    _ => Err(invalid("v1 address doesn't match the protocol")),
+                }
+            };
+            let port = |field: &str| field.parse::<u16>().map_err(|_| invalid("bad v1 port"));
+
+            (
+                Some(SocketAddr::new(ip(source)?, port(source_port)?)),
+                Some(SocketAddr::new(ip(destination)?, port(destination_port)?)),
+            )
+        }
+        _ => return Err(invalid("bad v1 fields")),
+    };
+
+    Ok(ProxyHeader {
+        version: 1,
+        source,
+        destination,
+        tlvs: vec![],
+    })
+}
+
+fn parse_v2(version_command: u8, family: u8, body: &[u8]) -> io::Result<ProxyHeader> {
+    if version_command >> 4 != 2 {
+        return Err(invalid("unsupported version"));
+    }
+    let local = match version_command & 0x0f {
+        0x0 => true,
+        0x1 => false,
+        _ => return Err(invalid("unsupported v2 command")),
+    };
+
+    let addresses_len = match family >> 4 {
+        0x0 => 0,
+        0x1 => 12,
+        0x2 => 36,
+        0x3 => 216,
+        _ => return Err(invalid("unsupported v2 address family")),
+    };
+    if body.len() < addresses_len {
+        return Err(invalid("v2 addresses truncated"));
+    }
+    let (addresses, mut tlvs_bytes) = body.split_at(addresses_len);
+
+    let (source, destination) = match family >> 4 {
+        _ if local => (None, None),
+        0x1 => {
+            let ip = |b: &[u8]| IpAddr::V4(Ipv4Addr::new(b[0], b[1], b[2], b[3]));
+            let port = |b: &[u8]| u16::from_be_bytes([b[0], b[1]]);
+            (
+                Some(SocketAddr::new(ip(&addresses[0..]), port(&addresses[8..]))),
+                Some(SocketAddr::new(ip(&addresses[4..]), port(&addresses[10..]))),
+            )
+        }
+        0x2 => {
+            let ip = |b: &[u8]| {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(&b[..16]);
+                IpAddr::V6(Ipv6Addr::from(octets))
+            };
+            let port = |b: &[u8]| u16::from_be_bytes([b[0], b[1]]);
+            (
+                Some(SocketAddr::new(ip(&addresses[0..]), port(&addresses[32..]))),
+                Some(SocketAddr::new(
+                    ip(&addresses[16..]),
+                    port(&addresses[34..]),
+                )),
+            )
+        }
+        _ => (None, None),
+    };
+
+    let mut tlvs = vec![];
+    while !tlvs_bytes.is_empty() {
+        if tlvs_bytes.len() < 3 {
+            return Err(invalid("v2 tlv truncated"));
+        }
+        let len = u16::from_be_bytes([tlvs_bytes[1], tlvs_bytes[2]]) as usize;
+        let value = tlvs_bytes
+            .get(3..3 + len)
+            .ok_or_else(|| invalid("v2 tlv truncated"))?;
+        tlvs.push((tlvs_bytes[0], value.to_vec()));
+        tlvs_bytes = &tlvs_bytes[3 + len..];
+    }
+
+    Ok(ProxyHeader {
+        version: 2,
+        source,
+        destination,
+        tlvs,
+    })
+}
@@ -0,0 +1,136 @@
+use std::fs;
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use rustls::sign::{self, SigningKey};
+use rustls::{Certificate, SignatureScheme};
+use webpki::EndEntityCert;
+
+use crate::pem::{load_certs, load_private_key};
+use crate::TlsAcceptor;
+
+/// Reloads the certificate of a `TlsAcceptor` from PEM files when they change.
+///
+/// Only for acceptors serving a single cert. The new config is a copy of the current one
+/// with just the reloaded cert, which replaces the whole cert resolver, so certs added
+/// with `TlsAcceptorBuilder::cert` or a custom resolver are dropped once the files
+/// change; reload those by building a new acceptor config and calling
+/// `TlsAcceptor::set_config`.
+pub struct PemFileWatcher {
+    acceptor: TlsAcceptor,
+    cert_chain_path: PathBuf,
+    private_key_path: PathBuf,
+    modified: (SystemTime, SystemTime),
+}
+
+impl PemFileWatcher {
+    /// The files are taken to be what `acceptor` serves now, only later changes reload.
+    pub fn new(
+        acceptor: &TlsAcceptor,
+        cert_chain_path: impl Into<PathBuf>,
+        private_key_path: impl Into<PathBuf>,
+    ) -> io::Result<Self> {
+        let cert_chain_path = cert_chain_path.into();
+        let private_key_path = private_key_path.into();
+        let modified = modified(&cert_chain_path, &private_key_path)?;
+
+        Ok(Self {
+            acceptor: acceptor.clone(),
+            cert_chain_path,
+            private_key_path,
+            modified,
+        })
+    }
+
+    /// Returns whether the config was replaced. On error the old config stays
+    /// and the next call tries again, e.g. when the cert has been rewritten but the
+    /// key not yet and they don't match.
+    pub fn reload_if_modified(&mut self) -> io::Result<bool> {
+        let modified = modified(&self.cert_chain_path, &self.private_key_path)?;
+        if self.modified == modified {
+            return Ok(false);
+        }
+
+        let certs = load_certs(&self.cert_chain_path)?;
+        let key = load_private_key(&self.private_key_path)?;
+        let signing_key = sign::any_supported_type(&key)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid key"))?;
+        verify_key_matches(&certs, &*signing_key)?;
+
+        let mut config = (*self.acceptor.config()).clone();
+        config
+            .set_single_cert(certs, key)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        self.acceptor.set_config(Arc::new(config));
+
+        self.modified = modified;
+
+        Ok(true)
+    }
+
+    /// Calls `reload_if_modified` every `interval` forever, e.g. in a spawned task.
+    pub async fn watch<F, T, E>(mut self, interval: Duration, timer: F, mut on_reload: E)
+    where
+        F: Fn(Duration) -> T,
+        T: Future,
+        E: FnMut(io::Result<bool>),
+    {
+        loop {
+            on_reload(self.reload_if_modified());
+
+            timer(interval).await;
+        }
+    }
+}
+
+fn modified(
+    cert_chain_path: &Path,
+    private_key_path: &Path,
+) -> io::Result<(SystemTime, SystemTime)> {
+    Ok((
+        fs::metadata(cert_chain_path)?.modified()?,
+        fs::metadata(private_key_path)?.modified()?,
+    ))
+}
+
+/// Signs a test message with `key` and checks it against the end-entity cert.
+fn verify_key_matches(cert_chain: &[Certificate], key: &dyn SigningKey) -> io::Result<()> {
+    const MESSAGE: &[u8] = b"async-tls-lite key check";
+
+    let mismatch = || {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "private key does not match the certificate",
+        )
+    };
+
+    let cert = cert_chain
+        .first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty cert chain"))?;
+    let cert = EndEntityCert::from(&cert.0)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid cert"))?;
+
+    let signer = key
+        .choose_scheme(&[
+            SignatureScheme::ED25519,
+            SignatureScheme::ECDSA_NISTP384_SHA384,
+            SignatureScheme::ECDSA_NISTP256_SHA256,
+            SignatureScheme::RSA_PKCS1_SHA256,
+        ])
+        .ok_or_else(mismatch)?;
+    let alg = match signer.get_scheme() {
+        SignatureScheme::ED25519 => &webpki::ED25519,
+        SignatureScheme::ECDSA_NISTP384_SHA384 => &webpki::ECDSA_P384_SHA384,
+        SignatureScheme::ECDSA_NISTP256_SHA256 => &webpki::ECDSA_P256_SHA256,
+        _ => &webpki::RSA_PKCS1_2048_8192_SHA256,
+    };
+    let signature = signer
+        .sign(MESSAGE)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+    cert.verify_signature(alg, MESSAGE, &signature)
+        .map_err(|_| mismatch())
+}
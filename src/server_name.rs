@@ -0,0 +1,125 @@
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::error::Error;
+
+/// What `TlsConnector::connect` verifies the server cert against.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ServerName {
+    DnsName(String),
+    /// Sent without SNI, per RFC 6066, and matched against the cert's IP SubjectAltNames.
+    IpAddress(IpAddr),
+}
+
+impl fmt::Display for ServerName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ServerName::DnsName(name) => write!(f, "{}", name),
+            ServerName::IpAddress(ip) => write!(f, "{}", ip),
+        }
+    }
+}
+
+pub trait IntoServerName {
+    fn into_server_name(self) -> io::Result<ServerName>;
+}
+
+/// Validates a `DnsName` like a `&str`.
+impl IntoServerName for ServerName {
+    fn into_server_name(self) -> io::Result<ServerName> {
+        match self {
+            ServerName::DnsName(name) => name.into_server_name(),
+            ServerName::IpAddress(_) => Ok(self),
+        }
+    }
+}
+
+/// An IP literal, or else a DNS name. Names shaped like `ip_placeholder`s are refused, the
+/// verifier would take them for IP addresses.
+impl<T> IntoServerName for &T
+where
+    T: AsRef<str> + ?Sized,
+{
+    fn into_server_name(self) -> io::Result<ServerName> {
+        let name = self.as_ref();
+
+        match name.parse::<IpAddr>() {
+            Ok(ip) => Ok(ServerName::IpAddress(ip)),
+            Err(_) => {
+                if webpki::DNSNameRef::try_from_ascii_str(name).is_err()
+                    || ip_from_placeholder(name).is_some()
+                {
+                    return Err(Error::InvalidServerName.into());
+                }
+                Ok(ServerName::DnsName(name.to_owned()))
+            }
+        }
+    }
+}
+
+impl IntoServerName for String {
+    fn into_server_name(self) -> io::Result<ServerName> {
+        self.as_str().into_server_name()
+    }
+}
+
+impl IntoServerName for IpAddr {
+    fn into_server_name(self) -> io::Result<ServerName> {
+        Ok(ServerName::IpAddress(self))
+    }
+}
+
+impl IntoServerName for Ipv4Addr {
+    fn into_server_name(self) -> io::Result<ServerName> {
+        IpAddr::V4(self).into_server_name()
+    }
+}
+
+impl IntoServerName for Ipv6Addr {
+    fn into_server_name(self) -> io::Result<ServerName> {
+        IpAddr::V6(self).into_server_name()
+    }
+}
+
+const IP_PLACEHOLDER_PREFIX: &str = "ip-";
+const IP_PLACEHOLDER_SUFFIX: &str = ".invalid";
+
+/// rustls 0.18 sessions need a DNS name, so an IP address is passed as one under the
+/// reserved `.invalid` TLD, e.g. `ip-7f000001.invalid`. It also keys the session cache.
+pub(crate) fn ip_placeholder(ip: IpAddr) -> String {
+    let octets = match ip {
+        IpAddr::V4(ip) => ip.octets().to_vec(),
+        IpAddr::V6(ip) => ip.octets().to_vec(),
+    };
+    let hex: String = octets.iter().map(|b| format!("{:02x}", b)).collect();
+
+    format!("{}{}{}", IP_PLACEHOLDER_PREFIX, hex, IP_PLACEHOLDER_SUFFIX)
+}
+
+pub(crate) fn ip_from_placeholder(name: &str) -> Option<IpAddr> {
+    let hex = name
+        .strip_prefix(IP_PLACEHOLDER_PREFIX)?
+        .strip_suffix(IP_PLACEHOLDER_SUFFIX)?;
+    if !hex.is_ascii() || hex.len() % 2 != 0 {
+        return None;
+    }
+    let octets = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+
+    match octets.len() {
+        4 => {
+            let mut ip = [0; 4];
+            ip.copy_from_slice(&octets);
+            Some(IpAddr::from(ip))
+        }
+        16 => {
+            let mut ip = [0; 16];
+            ip.copy_from_slice(&octets);
+            Some(IpAddr::from(ip))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, MutexGuard};
+
+#[cfg(feature = "connector")]
+use rustls::StoresClientSessions;
+#[cfg(feature = "acceptor")]
+use rustls::StoresServerSessions;
+
+/// An in-memory `StoresClientSessions` that evicts the least recently used entry once
+/// `capacity` is reached. rustls keys its entries by server name, up to two per server.
+#[cfg(feature = "connector")]
+pub struct LruClientSessionStore(Lru);
+
+/// An in-memory `StoresServerSessions` that evicts the least recently used session once
+/// `capacity` is reached.
+#[cfg(feature = "acceptor")]
+pub struct LruServerSessionStore(Lru);
+
+struct Lru {
+    capacity: usize,
+    inner: Mutex<LruInner>,
+}
+
+#[derive(Default)]
+struct LruInner {
+    entries: HashMap<Vec<u8>, (u64, Vec<u8>)>,
+    recency: BTreeMap<u64, Vec<u8>>,
+    tick: u64,
+}
+
+impl LruInner {
+    fn touch(&mut self, key: &[u8]) -> Option<&mut Vec<u8>> {
+        self.tick += 1;
+        let tick = self.tick;
+
+        let (used, value) = self.entries.get_mut(key)?;
+        let key = self.recency.remove(used).expect("recency out of sync");
+        *used = tick;
+        self.recency.insert(tick, key);
+
+        Some(value)
+    }
+}
+
+impl Lru {
+    fn new(capacity: usize) -> Self {
+        Lru {
+            capacity,
+            inner: Default::default(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, LruInner> {
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    fn put(&self, key: Vec<u8>, value: Vec<u8>) -> bool {
+        if self.capacity == 0 {
+            return false;
+        }
+
+        let mut lru = self.lock();
+
+        if let Some(stored) = lru.touch(&key) {
+            *stored = value;
+            return true;
+        }
+
+        if lru.entries.len() >= self.capacity {
+            if let Some((_, oldest)) = lru.recency.pop_first() {
+                lru.entries.remove(&oldest);
+            }
+        }
+
+        let tick = lru.tick;
+        lru.recency.insert(tick, key.clone());
+        lru.entries.insert(key, (tick, value));
+
+        true
+    }
+
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.lock().touch(key).cloned()
+    }
+
+    #[cfg_attr(not(feature = "acceptor"), allow(dead_code))]
+    fn take(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let mut lru = self.lock();
+
+        let (used, value) = lru.entries.remove(key)?;
+        lru.recency.remove(&used);
+
+        Some(value)
+    }
+}
+
+macro_rules! lru_store {
+    ($name:ident) => {
+        impl $name {
+            pub fn new(capacity: usize) -> Self {
+                $name(Lru::new(capacity))
+            }
+
+            pub fn capacity(&self) -> usize {
+                self.0.capacity
+            }
+
+            pub fn len(&self) -> usize {
+                self.0.len()
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.len() == 0
+            }
+        }
+    };
+}
+
+#[cfg(feature = "connector")]
+lru_store!(LruClientSessionStore);
+#[cfg(feature = "acceptor")]
+lru_store!(LruServerSessionStore);
+
+#[cfg(feature = "connector")]
+impl StoresClientSessions for LruClientSessionStore {
+    fn put(&self, key: Vec<u8>, value: Vec<u8>) -> bool {
+        self.0.put(key, value)
+    }
+
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.0.get(key)
+    }
+}
+
+#[cfg(feature = "acceptor")]
+impl StoresServerSessions for LruServerSessionStore {
+    fn put(&self, key: Vec<u8>, value: Vec<u8>) -> bool {
+        self.0.put(key, value)
+    }
+
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.0.get(key)
+    }
+
+    fn take(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.0.take(key)
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+use std::io;
+
+use rustls::sign::CertifiedKey;
+use rustls::{ClientHello, ResolvesServerCert};
+use webpki::DNSNameRef;
+
+/// Picks the certificate by SNI hostname: an exact name first, then a `*.` wildcard
+/// covering one label, then the default.
+#[derive(Default, Clone)]
+pub struct SniCertResolver {
+    exact: HashMap<String, CertifiedKey>,
+    wildcard: HashMap<String, CertifiedKey>,
+    default: Option<CertifiedKey>,
+}
+
+impl SniCertResolver {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// `name` is a hostname like `example.com` or a pattern like `*.example.com`.
+    pub fn add(&mut self, name: &str, key: CertifiedKey) -> io::Result<()> {
+        let name = name.to_ascii_lowercase();
+
+        match name.strip_prefix("*.") {
+            Some(suffix) => {
+                DNSNameRef::try_from_ascii_str(suffix)
+                    .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid name"))?;
+                key.cross_check_end_entity_cert(None)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+                self.wildcard.insert(suffix.to_owned(), key);
+            }
+            None => {
+                let dns_name = DNSNameRef::try_from_ascii_str(&name)
+                    .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid name"))?;
+                key.cross_check_end_entity_cert(Some(dns_name))
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+                self.exact.insert(name, key);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Used when the client sends no SNI or nothing matches.
+    pub fn set_default(&mut self, key: CertifiedKey) -> io::Result<()> {
+        key.cross_check_end_entity_cert(None)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+        self.default = Some(key);
+
+        Ok(())
+    }
+
+    pub fn get(&self, server_name: Option<&str>) -> Option<&CertifiedKey> {
+        server_name
+            .map(|name| name.to_ascii_lowercase())
+            .and_then(|name| {
+                self.exact.get(&name).or_else(|| {
+                    name.split_once('.')
+                        .and_then(|(_, suffix)| self.wildcard.get(suffix))
+                })
+            })
+            .or(self.default.as_ref())
+    }
+}
+
+impl ResolvesServerCert for SniCertResolver {
+    fn resolve(&self, client_hello: ClientHello) -> Option<CertifiedKey> {
+        self.get(client_hello.server_name().map(Into::into))
+            .cloned()
+    }
+}
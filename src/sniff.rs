@@ -0,0 +1,99 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_util::io::{AsyncRead, AsyncWrite};
+use rustls::internal::msgs::enums::ContentType;
+use rustls::ServerSession;
+
+use crate::TlsStream;
+
+/// Whether `peeked` starts with the content type of a handshake record. No text protocol
+/// starts with it, so one byte is enough to decide.
+pub(crate) fn is_tls_handshake(peeked: &[u8]) -> bool {
+    peeked.first() == Some(&ContentType::Handshake.get_u8())
+}
+
+/// What `TlsAcceptor::accept_or_plaintext` found on the connection.
+#[allow(clippy::large_enum_variant)]
+pub enum MaybeTls<S> {
+    Tls(TlsStream<ServerSession, S>),
+    Plaintext(PrefixedStream<S>),
+}
+
+/// A stream that first reads `prefix`, the bytes already read from `inner`.
+pub struct PrefixedStream<S> {
+    prefix: Vec<u8>,
+    pos: usize,
+    inner: S,
+}
+
+impl<S> PrefixedStream<S> {
+    pub fn new(prefix: Vec<u8>, inner: S) -> Self {
+        PrefixedStream {
+            prefix,
+            pos: 0,
+            inner,
+        }
+    }
+
+    /// The part of the prefix not read yet.
+    pub fn prefix(&self) -> &[u8] {
+        &self.prefix[self.pos..]
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// The transport and the part of the prefix not read yet.
+    pub fn into_inner(mut self) -> (S, Vec<u8>) {
+        self.prefix.drain(..self.pos);
+        (self.inner, self.prefix)
+    }
+}
+
+impl<S> AsyncRead for PrefixedStream<S>
+where
+    S: AsyncRead + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        let prefix = &this.prefix[this.pos..];
+        if prefix.is_empty() || buf.is_empty() {
+            return Pin::new(&mut this.inner).poll_read(cx, buf);
+        }
+
+        let n = prefix.len().min(buf.len());
+        buf[..n].copy_from_slice(&prefix[..n]);
+        this.pos += n;
+
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl<S> AsyncWrite for PrefixedStream<S>
+where
+    S: AsyncWrite + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_close(cx)
+    }
+}
@@ -0,0 +1,150 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+
+use futures_util::io::{AsyncRead, AsyncWrite};
+use rustls::Session;
+
+use crate::{Eof, TlsStream, TlsStreamInner};
+
+pub struct ReadHalf<SESS, S> {
+    inner: Arc<Mutex<TlsStreamInner<SESS, S>>>,
+}
+
+pub struct WriteHalf<SESS, S> {
+    inner: Arc<Mutex<TlsStreamInner<SESS, S>>>,
+}
+
+impl<SESS, S> TlsStream<SESS, S> {
+    /// Splits into halves that can be driven from different tasks.
+    ///
+    /// Both halves share the session and the transport behind a lock that is only held
+    /// for the duration of a single poll. The read half never writes to the transport,
+    /// so it can't take the writer's wakeup. Whatever reading queues to send (e.g. an
+    /// alert reply) is left to the write half, and the task that last polled it is woken.
+    pub fn split(self) -> (ReadHalf<SESS, S>, WriteHalf<SESS, S>) {
+        let inner = Arc::new(Mutex::new(self.inner));
+
+        (
+            ReadHalf {
+                inner: inner.clone(),
+            },
+            WriteHalf { inner },
+        )
+    }
+}
+
+impl<SESS, S> ReadHalf<SESS, S> {
+    pub fn is_pair_of(&self, other: &WriteHalf<SESS, S>) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    pub fn reunite(
+        self,
+        other: WriteHalf<SESS, S>,
+    ) -> Result<TlsStream<SESS, S>, ReuniteError<SESS, S>> {
+        reunite(self, other)
+    }
+
+    pub fn eof(&self) -> Option<Eof> {
+        lock(&self.inner).eof
+    }
+}
+
+impl<SESS, S> WriteHalf<SESS, S> {
+    pub fn is_pair_of(&self, other: &ReadHalf<SESS, S>) -> bool {
+        other.is_pair_of(self)
+    }
+
+    pub fn reunite(
+        self,
+        other: ReadHalf<SESS, S>,
+    ) -> Result<TlsStream<SESS, S>, ReuniteError<SESS, S>> {
+        reunite(other, self)
+    }
+}
+
+fn reunite<SESS, S>(
+    read: ReadHalf<SESS, S>,
+    write: WriteHalf<SESS, S>,
+) -> Result<TlsStream<SESS, S>, ReuniteError<SESS, S>> {
+    if !read.is_pair_of(&write) {
+        return Err(ReuniteError(read, write));
+    }
+
+    drop(write);
+
+    let inner = match Arc::try_unwrap(read.inner) {
+        Ok(inner) => inner,
+        Err(_) => unreachable!("both halves were given"),
+    };
+    let inner = inner.into_inner().unwrap_or_else(|err| err.into_inner());
+
+    Ok(TlsStream { inner })
+}
+
+fn lock<SESS, S>(
+    inner: &Mutex<TlsStreamInner<SESS, S>>,
+) -> MutexGuard<'_, TlsStreamInner<SESS, S>> {
+    inner.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+pub struct ReuniteError<SESS, S>(pub ReadHalf<SESS, S>, pub WriteHalf<SESS, S>);
+
+impl<SESS, S> fmt::Debug for ReuniteError<SESS, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("ReuniteError").finish()
+    }
+}
+
+impl<SESS, S> fmt::Display for ReuniteError<SESS, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "tried to reunite halves that are not from the same TlsStream"
+        )
+    }
+}
+
+impl<SESS, S> error::Error for ReuniteError<SESS, S> {}
+
+impl<SESS, S> AsyncRead for ReadHalf<SESS, S>
+where
+    SESS: Session + Unpin,
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        lock(&self.inner).poll_read(cx, buf, false)
+    }
+}
+
+impl<SESS, S> AsyncWrite for WriteHalf<SESS, S>
+where
+    SESS: Session + Unpin,
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let mut inner = lock(&self.inner);
+        inner.register_writer(cx);
+        inner.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let mut inner = lock(&self.inner);
+        inner.register_writer(cx);
+        inner.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let mut inner = lock(&self.inner);
+        inner.register_writer(cx);
+        inner.poll_close(cx)
+    }
+}
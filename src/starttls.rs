@@ -0,0 +1,395 @@
+use std::io;
+
+use futures_util::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+#[cfg(feature = "connector")]
+use rustls::ClientSession;
+#[cfg(feature = "acceptor")]
+use rustls::ServerSession;
+
+#[cfg(feature = "acceptor")]
+use crate::timeout::before_deadline;
+#[cfg(feature = "acceptor")]
+use crate::TlsAcceptor;
+use crate::TlsStream;
+#[cfg(feature = "connector")]
+use crate::{IntoServerName, TlsConnector};
+
+#[cfg(feature = "connector")]
+const MAX_LINE_LEN: usize = 8192;
+/// What RFC 5321 allows for an SMTP command line, plenty for the few commands taken
+/// before the upgrade.
+#[cfg(feature = "acceptor")]
+const MAX_COMMAND_LEN: usize = 512;
+/// How many commands a client may send before the upgrade.
+#[cfg(feature = "acceptor")]
+const MAX_COMMANDS: usize = 16;
+/// `SSLRequest` is this code as if it were a protocol version, after the message length.
+const POSTGRES_SSL_REQUEST: [u8; 8] = [0, 0, 0, 8, 0x04, 0xd2, 0x16, 0x2f];
+#[cfg(feature = "acceptor")]
+const POSTGRES_GSSENC_REQUEST: [u8; 8] = [0, 0, 0, 8, 0x04, 0xd2, 0x16, 0x30];
+
+fn refused(protocol: &str, line: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{} upgrade refused: {}", protocol, line.trim_end()),
+    )
+}
+
+/// A line without the CRLF, at most `max_len` bytes with it.
+async fn read_line<S>(stream: &mut S, max_len: usize) -> io::Result<String>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut line = vec![];
+    let mut byte = [0; 1];
+    while !line.ends_with(b"\n") {
+        if line.len() == max_len {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "line too long"));
+        }
+        stream.read_exact(&mut byte).await?;
+        line.push(byte[0]);
+    }
+
+    let line = String::from_utf8(line)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "line isn't utf-8"))?;
+    Ok(line.trim_end_matches(&['\r', '\n'][..]).to_owned())
+}
+
+async fn write_line<S>(stream: &mut S, line: &str) -> io::Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
+    stream.write_all(format!("{}\r\n", line).as_bytes()).await?;
+    stream.flush().await
+}
+
+/// The lines of a possibly multiline SMTP reply, fails unless its code is `code`.
+#[cfg(feature = "connector")]
+async fn read_smtp_reply<S>(stream: &mut S, code: &str) -> io::Result<Vec<String>>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut lines = vec![];
+    loop {
+        let line = read_line(stream, MAX_LINE_LEN).await?;
+        if !line.starts_with(code) {
+            return Err(refused("smtp", &line));
+        }
+        let last = line.as_bytes().get(3) != Some(&b'-');
+        lines.push(line);
+        if last {
+            return Ok(lines);
+        }
+    }
+}
+
+/// Sends `EHLO ehlo_name` and `STARTTLS`, then connects.
+#[cfg(feature = "connector")]
+pub async fn smtp_connect<S>(
+    connector: &TlsConnector,
+    server_name: impl IntoServerName,
+    mut stream: S,
+    ehlo_name: &str,
+) -> io::Result<TlsStream<ClientSession, S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    read_smtp_reply(&mut stream, "220").await?;
+
+    write_line(&mut stream, &format!("EHLO {}", ehlo_name)).await?;
+    let extensions = read_smtp_reply(&mut stream, "250").await?;
+    if !extensions
+        .iter()
+        .any(|line| matches!(line.get(4..), Some(ext) if ext.eq_ignore_ascii_case("STARTTLS")))
+    {
+        return Err(refused("smtp", "STARTTLS not advertised"));
+    }
+
+    write_line(&mut stream, "STARTTLS").await?;
+    read_smtp_reply(&mut stream, "220").await?;
+
+    connector.connect(server_name, stream).await
+}
+
+/// Greets as `hostname`, answers `EHLO` advertising `STARTTLS` and refuses anything else
+/// but `STARTTLS` and `QUIT`, then accepts.
+///
+/// Fails on a command line over 512 bytes or after 16 commands without `STARTTLS`. The
+/// handshake deadline, if any, covers the commands too.
+#[cfg(feature = "acceptor")]
+pub async fn smtp_accept<S>(
+    acceptor: &TlsAcceptor,
+    mut stream: S,
+    hostname: &str,
+) -> io::Result<TlsStream<ServerSession, S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut timer = acceptor.handshake_timer();
+    before_deadline(timer.as_mut(), smtp_negotiate(&mut stream, hostname)).await?;
+
+    acceptor.accept_with_timer(stream, timer).await
+}
+
+/// Returns once `STARTTLS` is answered.
+#[cfg(feature = "acceptor")]
+async fn smtp_negotiate<S>(stream: &mut S, hostname: &str) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    write_line(stream, &format!("220 {} ESMTP", hostname)).await?;
+
+    for _ in 0..MAX_COMMANDS {
+        let line = read_line(stream, MAX_COMMAND_LEN).await?;
+        let command = line
+            .split(' ')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_uppercase();
+
+        match command.as_str() {
+            "EHLO" => write_line(stream, &format!("250-{}\r\n250 STARTTLS", hostname)).await?,
+            "HELO" => write_line(stream, &format!("250 {}", hostname)).await?,
+            "STARTTLS" => return write_line(stream, "220 Ready to start TLS").await,
+            "QUIT" => {
+                write_line(stream, "221 Bye").await?;
+                return Err(refused("smtp", &line));
+            }
+            _ => write_line(stream, "530 Must issue a STARTTLS command first").await?,
+        }
+    }
+
+    write_line(stream, &format!("421 {} Too many commands", hostname)).await?;
+    Err(refused("smtp", "too many commands"))
+}
+
+/// Sends `STARTTLS` tagged `a1`, then connects.
+#[cfg(feature = "connector")]
+pub async fn imap_connect<S>(
+    connector: &TlsConnector,
+    server_name: impl IntoServerName,
+    mut stream: S,
+) -> io::Result<TlsStream<ClientSession, S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let greeting = read_line(&mut stream, MAX_LINE_LEN).await?;
+    if !greeting.starts_with("* OK") {
+        return Err(refused("imap", &greeting));
+    }
+
+    write_line(&mut stream, "a1 STARTTLS").await?;
+    loop {
+        let line = read_line(&mut stream, MAX_LINE_LEN).await?;
+        if line.starts_with("a1 OK") {
+            break;
+        }
+        if line.starts_with("a1 ") {
+            return Err(refused("imap", &line));
+        }
+    }
+
+    connector.connect(server_name, stream).await
+}
+
+/// Answers `CAPABILITY` advertising `STARTTLS` and refuses anything else but `STARTTLS`
+/// and `LOGOUT`, then accepts.
+///
+/// Fails on a command line over 512 bytes or after 16 commands without `STARTTLS`. The
+/// handshake deadline, if any, covers the commands too.
+#[cfg(feature = "acceptor")]
+pub async fn imap_accept<S>(
+    acceptor: &TlsAcceptor,
+    mut stream: S,
+) -> io::Result<TlsStream<ServerSession, S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut timer = acceptor.handshake_timer();
+    before_deadline(timer.as_mut(), imap_negotiate(&mut stream)).await?;
+
+    acceptor.accept_with_timer(stream, timer).await
+}
+
+/// Returns once `STARTTLS` is answered.
+#[cfg(feature = "acceptor")]
+async fn imap_negotiate<S>(stream: &mut S) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    const CAPABILITY: &str = "IMAP4rev1 STARTTLS LOGINDISABLED";
+
+    write_line(stream, &format!("* OK [CAPABILITY {}] Ready", CAPABILITY)).await?;
+
+    for _ in 0..MAX_COMMANDS {
+        let line = read_line(stream, MAX_COMMAND_LEN).await?;
+        let mut words = line.split(' ');
+        let tag = words.next().unwrap_or_default();
+        let command = words.next().unwrap_or_default().to_ascii_uppercase();
+
+        match command.as_str() {
+            "CAPABILITY" => {
+                let reply = format!(
+                    "* CAPABILITY {}\r\n{} OK CAPABILITY completed",
+                    CAPABILITY, tag
+                );
+                write_line(stream, &reply).await?
+            }
+            "STARTTLS" => {
+                return write_line(stream, &format!("{} OK Begin TLS negotiation now", tag)).await
+            }
+            "LOGOUT" => {
+                let reply = format!("* BYE Logging out\r\n{} OK LOGOUT completed", tag);
+                write_line(stream, &reply).await?;
+                return Err(refused("imap", &line));
+            }
+            _ => write_line(stream, &format!("{} BAD Must issue STARTTLS first", tag)).await?,
+        }
+    }
+
+    write_line(stream, "* BYE Too many commands").await?;
+    Err(refused("imap", "too many commands"))
+}
+
+/// Sends `STLS`, then connects.
+#[cfg(feature = "connector")]
+pub async fn pop3_connect<S>(
+    connector: &TlsConnector,
+    server_name: impl IntoServerName,
+    mut stream: S,
+) -> io::Result<TlsStream<ClientSession, S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let greeting = read_line(&mut stream, MAX_LINE_LEN).await?;
+    if !greeting.starts_with("+OK") {
+        return Err(refused("pop3", &greeting));
+    }
+
+    write_line(&mut stream, "STLS").await?;
+    let line = read_line(&mut stream, MAX_LINE_LEN).await?;
+    if !line.starts_with("+OK") {
+        return Err(refused("pop3", &line));
+    }
+
+    connector.connect(server_name, stream).await
+}
+
+/// Answers `CAPA` advertising `STLS` and refuses anything else but `STLS` and `QUIT`, then
+/// accepts.
+///
+/// Fails on a command line over 512 bytes or after 16 commands without `STLS`. The
+/// handshake deadline, if any, covers the commands too.
+#[cfg(feature = "acceptor")]
+pub async fn pop3_accept<S>(
+    acceptor: &TlsAcceptor,
+    mut stream: S,
+) -> io::Result<TlsStream<ServerSession, S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut timer = acceptor.handshake_timer();
+    before_deadline(timer.as_mut(), pop3_negotiate(&mut stream)).await?;
+
+    acceptor.accept_with_timer(stream, timer).await
+}
+
+/// Returns once `STLS` is answered.
+#[cfg(feature = "acceptor")]
+async fn pop3_negotiate<S>(stream: &mut S) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    write_line(stream, "+OK Ready").await?;
+
+    for _ in 0..MAX_COMMANDS {
+        let line = read_line(stream, MAX_COMMAND_LEN).await?;
+        let command = line
+            .split(' ')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_uppercase();
+
+        match command.as_str() {
+            "CAPA" => write_line(stream, "+OK\r\nSTLS\r\n.").await?,
+            "STLS" => return write_line(stream, "+OK Begin TLS negotiation").await,
+            "QUIT" => {
+                write_line(stream, "+OK Bye").await?;
+                return Err(refused("pop3", &line));
+            }
+            _ => write_line(stream, "-ERR Must issue STLS first").await?,
+        }
+    }
+
+    write_line(stream, "-ERR Too many commands").await?;
+    Err(refused("pop3", "too many commands"))
+}
+
+/// Sends an `SSLRequest`, then connects.
+#[cfg(feature = "connector")]
+pub async fn postgres_connect<S>(
+    connector: &TlsConnector,
+    server_name: impl IntoServerName,
+    mut stream: S,
+) -> io::Result<TlsStream<ClientSession, S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    stream.write_all(&POSTGRES_SSL_REQUEST).await?;
+    stream.flush().await?;
+
+    let mut answer = [0; 1];
+    stream.read_exact(&mut answer).await?;
+    if &answer != b"S" {
+        return Err(refused(
+            "postgres",
+            &format!("SSLRequest answered {:?}", answer[0] as char),
+        ));
+    }
+
+    connector.connect(server_name, stream).await
+}
+
+/// Declines a `GSSENCRequest`, accepts after an `SSLRequest` and fails on anything else,
+/// e.g. a plaintext `StartupMessage`.
+///
+/// The handshake deadline, if any, covers the requests too.
+#[cfg(feature = "acceptor")]
+pub async fn postgres_accept<S>(
+    acceptor: &TlsAcceptor,
+    mut stream: S,
+) -> io::Result<TlsStream<ServerSession, S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut timer = acceptor.handshake_timer();
+    before_deadline(timer.as_mut(), postgres_negotiate(&mut stream)).await?;
+
+    acceptor.accept_with_timer(stream, timer).await
+}
+
+/// Returns once an `SSLRequest` is answered.
+#[cfg(feature = "acceptor")]
+async fn postgres_negotiate<S>(stream: &mut S) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    for _ in 0..MAX_COMMANDS {
+        let mut request = [0; 8];
+        stream.read_exact(&mut request).await?;
+
+        match request {
+            POSTGRES_SSL_REQUEST => {
+                stream.write_all(b"S").await?;
+                return stream.flush().await;
+            }
+            POSTGRES_GSSENC_REQUEST => {
+                stream.write_all(b"N").await?;
+                stream.flush().await?;
+            }
+            _ => return Err(refused("postgres", "expected SSLRequest")),
+        }
+    }
+
+    Err(refused("postgres", "too many requests"))
+}
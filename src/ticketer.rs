@@ -0,0 +1,128 @@
+use std::convert::TryFrom;
+use std::io;
+use std::iter;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use rustls::ProducesTickets;
+
+/// Encrypts stateless session tickets with a ChaCha20-Poly1305 key that is replaced every
+/// `key_lifetime`. Tickets of the previous key are still accepted, so a ticket lives at
+/// most twice as long.
+pub struct RotatingTicketer {
+    key_lifetime: Duration,
+    rng: SystemRandom,
+    keys: Mutex<TicketKeys>,
+}
+
+struct TicketKeys {
+    current: LessSafeKey,
+    previous: Option<LessSafeKey>,
+    rotated_at: Instant,
+}
+
+impl RotatingTicketer {
+    pub fn new(key_lifetime: Duration) -> io::Result<Self> {
+        let rng = SystemRandom::new();
+        let keys = TicketKeys {
+            current: generate_key(&rng)?,
+            previous: None,
+            rotated_at: Instant::now(),
+        };
+
+        Ok(RotatingTicketer {
+            key_lifetime,
+            rng,
+            keys: Mutex::new(keys),
+        })
+    }
+
+    pub fn key_lifetime(&self) -> Duration {
+        self.key_lifetime
+    }
+
+    fn keys(&self) -> Option<MutexGuard<'_, TicketKeys>> {
+        let mut keys = self.keys.lock().unwrap_or_else(|err| err.into_inner());
+
+        let elapsed = keys.rotated_at.elapsed();
+        if elapsed >= self.key_lifetime {
+            let current = generate_key(&self.rng).ok()?;
+            let previous = std::mem::replace(&mut keys.current, current);
+            // A lifetime too long to double never runs out.
+            let previous_expired = match self.key_lifetime.checked_mul(2) {
+                Some(lifetime) => elapsed >= lifetime,
+                None => false,
+            };
+            keys.previous = if !previous_expired {
+                Some(previous)
+            } else {
+                None
+            };
+            keys.rotated_at = Instant::now();
+        }
+
+        Some(keys)
+    }
+}
+
+fn generate_key(rng: &SystemRandom) -> io::Result<LessSafeKey> {
+    let mut key = [0; 32];
+    rng.fill(&mut key)
+        .map_err(|_| io::Error::new(io::ErrorKind::NotFound, "failed to generate ticket key"))?;
+    let key = UnboundKey::new(&CHACHA20_POLY1305, &key).expect("invalid key length");
+
+    Ok(LessSafeKey::new(key))
+}
+
+impl ProducesTickets for RotatingTicketer {
+    fn enabled(&self) -> bool {
+        true
+    }
+
+    fn get_lifetime(&self) -> u32 {
+        u32::try_from(self.key_lifetime.as_secs()).unwrap_or(u32::MAX)
+    }
+
+    fn encrypt(&self, plain: &[u8]) -> Option<Vec<u8>> {
+        let mut nonce = [0; NONCE_LEN];
+        self.rng.fill(&mut nonce).ok()?;
+
+        let mut ticket = nonce.to_vec();
+        ticket.extend_from_slice(plain);
+        let tag = self
+            .keys()?
+            .current
+            .seal_in_place_separate_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut ticket[NONCE_LEN..],
+            )
+            .ok()?;
+        ticket.extend_from_slice(tag.as_ref());
+
+        Some(ticket)
+    }
+
+    fn decrypt(&self, cipher: &[u8]) -> Option<Vec<u8>> {
+        if cipher.len() < NONCE_LEN + CHACHA20_POLY1305.tag_len() {
+            return None;
+        }
+        let (nonce, sealed) = cipher.split_at(NONCE_LEN);
+
+        let keys = self.keys()?;
+        iter::once(&keys.current)
+            .chain(keys.previous.as_ref())
+            .find_map(|key| {
+                let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+                let mut plain = sealed.to_vec();
+                let len = key
+                    .open_in_place(nonce, Aad::empty(), &mut plain)
+                    .ok()?
+                    .len();
+                plain.truncate(len);
+                Some(plain)
+            })
+    }
+}
@@ -0,0 +1,64 @@
+use std::future::Future;
+#[cfg(feature = "acceptor")]
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(feature = "acceptor")]
+use futures_util::future::{select, Either};
+#[cfg(feature = "acceptor")]
+use futures_util::pin_mut;
+
+#[cfg(feature = "acceptor")]
+use crate::error::Error;
+
+pub(crate) type Timer = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// A handshake deadline, the timer future comes from the caller so any runtime works.
+#[derive(Clone)]
+pub(crate) struct HandshakeTimeout {
+    duration: Duration,
+    timer: Arc<dyn Fn(Duration) -> Timer + Send + Sync>,
+}
+
+impl HandshakeTimeout {
+    pub(crate) fn new<F, T>(duration: Duration, timer: F) -> Self
+    where
+        F: Fn(Duration) -> T + Send + Sync + 'static,
+        T: Future + Send + 'static,
+    {
+        Self {
+            duration,
+            timer: Arc::new(move |duration| {
+                let timer = timer(duration);
+                Box::pin(async move {
+                    timer.await;
+                })
+            }),
+        }
+    }
+
+    pub(crate) fn timer(&self) -> Timer {
+        (self.timer)(self.duration)
+    }
+}
+
+/// Runs `future`, the part before the handshake, e.g. reading a PROXY header, failing when
+/// `timer` completes first. The rest of `timer` is then left for the handshake.
+#[cfg(feature = "acceptor")]
+pub(crate) async fn before_deadline<T>(
+    timer: Option<&mut Timer>,
+    future: impl Future<Output = io::Result<T>>,
+) -> io::Result<T> {
+    let timer = match timer {
+        Some(timer) => timer,
+        None => return future.await,
+    };
+
+    pin_mut!(future);
+    match select(future, timer).await {
+        Either::Left((ret, _)) => ret,
+        Either::Right(_) => Err(Error::HandshakeTimeout.into()),
+    }
+}
@@ -0,0 +1,156 @@
+use std::net::IpAddr;
+use std::time::SystemTime;
+
+use rustls::{Certificate, RootCertStore, ServerCertVerified, ServerCertVerifier, TLSError};
+use webpki::{
+    DNSNameRef, EndEntityCert, SignatureAlgorithm, TLSServerTrustAnchors, Time, TrustAnchor,
+};
+
+use crate::pem::der;
+use crate::server_name::ip_from_placeholder;
+
+/// The same as `rustls::WebPKIVerifier`.
+static SUPPORTED_SIG_ALGS: &[&SignatureAlgorithm] = &[
+    &webpki::ECDSA_P256_SHA256,
+    &webpki::ECDSA_P256_SHA384,
+    &webpki::ECDSA_P384_SHA256,
+    &webpki::ECDSA_P384_SHA384,
+    &webpki::ED25519,
+    &webpki::RSA_PSS_2048_8192_SHA256_LEGACY_KEY,
+    &webpki::RSA_PSS_2048_8192_SHA384_LEGACY_KEY,
+    &webpki::RSA_PSS_2048_8192_SHA512_LEGACY_KEY,
+    &webpki::RSA_PKCS1_2048_8192_SHA256,
+    &webpki::RSA_PKCS1_2048_8192_SHA384,
+    &webpki::RSA_PKCS1_2048_8192_SHA512,
+    &webpki::RSA_PKCS1_3072_8192_SHA384,
+];
+
+pub(crate) fn trust_anchors(roots: &RootCertStore) -> Vec<TrustAnchor<'_>> {
+    roots
+        .roots
+        .iter()
+        .map(|root| root.to_trust_anchor())
+        .collect()
+}
+
+/// What `rustls::WebPKIVerifier` checks, except the server name.
+pub(crate) fn verify_chain(
+    trust_anchors: &[TrustAnchor],
+    presented_certs: &[Certificate],
+) -> Result<(), TLSError> {
+    let (end_entity, intermediates) = presented_certs
+        .split_first()
+        .ok_or(TLSError::NoCertificatesPresented)?;
+
+    let cert = EndEntityCert::from(&end_entity.0).map_err(TLSError::WebPKIError)?;
+    let chain: Vec<&[u8]> = intermediates.iter().map(|cert| cert.0.as_ref()).collect();
+    let now = Time::try_from(SystemTime::now()).map_err(|_| TLSError::FailedToGetCurrentTime)?;
+
+    cert.verify_is_valid_tls_server_cert(
+        SUPPORTED_SIG_ALGS,
+        &TLSServerTrustAnchors(trust_anchors),
+        &chain,
+        now,
+    )
+    .map_err(TLSError::WebPKIError)
+}
+
+/// `rustls::WebPKIVerifier`, plus IP addresses passed as `ip_placeholder` names and the
+/// `dangerous` options.
+#[derive(Default)]
+pub(crate) struct ServerVerifier {
+    pub(crate) accept_invalid_certs: bool,
+    pub(crate) accept_invalid_hostnames: bool,
+}
+
+impl ServerCertVerifier for ServerVerifier {
+    fn verify_server_cert(
+        &self,
+        roots: &RootCertStore,
+        presented_certs: &[Certificate],
+        dns_name: DNSNameRef,
+        _ocsp_response: &[u8],
+    ) -> Result<ServerCertVerified, TLSError> {
+        if self.accept_invalid_certs {
+            return Ok(ServerCertVerified::assertion());
+        }
+
+        verify_chain(&trust_anchors(roots), presented_certs)?;
+
+        if !self.accept_invalid_hostnames {
+            let end_entity = &presented_certs[0];
+
+            match ip_from_placeholder(dns_name.into()) {
+                Some(ip) => verify_ip_address(end_entity, ip)?,
+                None => EndEntityCert::from(&end_entity.0)
+                    .and_then(|cert| cert.verify_is_valid_for_dns_name(dns_name))
+                    .map_err(TLSError::WebPKIError)?,
+            }
+        }
+
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+fn verify_ip_address(cert: &Certificate, ip: IpAddr) -> Result<(), TLSError> {
+    let octets = match ip {
+        IpAddr::V4(ip) => ip.octets().to_vec(),
+        IpAddr::V6(ip) => ip.octets().to_vec(),
+    };
+
+    if subject_alt_name_ip_addresses(&cert.0)
+        .unwrap_or_default()
+        .contains(&octets.as_slice())
+    {
+        Ok(())
+    } else {
+        Err(TLSError::WebPKIError(webpki::Error::CertNotValidForName))
+    }
+}
+
+const OID_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+const TAG_EXTENSIONS: u8 = 0xa3;
+const TAG_IP_ADDRESS: u8 = 0x87;
+
+/// The `iPAddress` entries of the subjectAltName extension, webpki 0.21 ignores them.
+fn subject_alt_name_ip_addresses(cert: &[u8]) -> Option<Vec<&[u8]>> {
+    let (_, certificate, _) = der::read(cert)?;
+    let (_, mut tbs_certificate, _) = der::read(certificate)?;
+
+    let mut extensions = loop {
+        let (tag, value, rest) = der::read(tbs_certificate)?;
+        if tag == TAG_EXTENSIONS {
+            break der::read(value)?.1;
+        }
+        tbs_certificate = rest;
+    };
+    while !extensions.is_empty() {
+        let (_, extension, rest) = der::read(extensions)?;
+        extensions = rest;
+
+        let (_, oid, extension) = der::read(extension)?;
+        if oid != OID_SUBJECT_ALT_NAME {
+            continue;
+        }
+        // critical (optional), then extnValue
+        let (tag, value, rest) = der::read(extension)?;
+        let value = if tag == der::OCTET_STRING {
+            value
+        } else {
+            der::read(rest)?.1
+        };
+
+        let (_, mut general_names, _) = der::read(value)?;
+        let mut ip_addresses = vec![];
+        while !general_names.is_empty() {
+            let (tag, value, rest) = der::read(general_names)?;
+            if tag == TAG_IP_ADDRESS {
+                ip_addresses.push(value);
+            }
+            general_names = rest;
+        }
+        return Some(ip_addresses);
+    }
+
+    None
+}
@@ -0,0 +1,74 @@
+#![cfg(all(feature = "acceptor", feature = "connector"))]
+
+use std::io;
+
+use futures_executor::block_on;
+use futures_util::future::try_join;
+use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+
+mod helper;
+
+async fn handle_h2<S: AsyncWriteExt + Unpin>(mut stream: S) -> io::Result<()> {
+    stream.write_all(b"h2 handler").await?;
+    stream.close().await
+}
+
+async fn handle_http1<S: AsyncWriteExt + Unpin>(mut stream: S) -> io::Result<()> {
+    stream.write_all(b"http/1.1 handler").await?;
+    stream.close().await
+}
+
+/// What the client reads from the handler the server dispatched to.
+async fn dispatch(client_protocols: &[&[u8]]) -> io::Result<(Option<Vec<u8>>, String)> {
+    let acceptor = helper::acceptor_builder("tls.lvh.me")
+        .alpn_protocols(vec![b"h2".to_vec(), b"http/1.1".to_vec()])
+        .build()?;
+    let connector = helper::connector_builder()
+        .alpn_protocols(client_protocols.iter().map(|p| p.to_vec()).collect())
+        .build()?;
+
+    let (stream_c, stream_s) = helper::duplex();
+
+    let server = async {
+        let (alpn_protocol, tls_stream) = acceptor.accept_with_alpn(stream_s).await?;
+        match alpn_protocol.as_deref() {
+            Some(b"h2") => handle_h2(tls_stream).await?,
+            _ => handle_http1(tls_stream).await?,
+        }
+        Ok::<_, io::Error>(alpn_protocol)
+    };
+    let client = async {
+        let mut tls_stream = connector.connect("tls.lvh.me", stream_c).await?;
+        let mut buf = String::new();
+        tls_stream.read_to_string(&mut buf).await?;
+        Ok::<_, io::Error>(buf)
+    };
+
+    try_join(server, client).await
+}
+
+#[test]
+fn negotiated() -> io::Result<()> {
+    block_on(async {
+        let (alpn_protocol, response) = dispatch(&[b"h2", b"http/1.1"]).await?;
+        assert_eq!(alpn_protocol, Some(b"h2".to_vec()));
+        assert_eq!(response, "h2 handler");
+
+        let (alpn_protocol, response) = dispatch(&[b"http/1.1"]).await?;
+        assert_eq!(alpn_protocol, Some(b"http/1.1".to_vec()));
+        assert_eq!(response, "http/1.1 handler");
+
+        Ok(())
+    })
+}
+
+#[test]
+fn not_negotiated() -> io::Result<()> {
+    block_on(async {
+        let (alpn_protocol, response) = dispatch(&[]).await?;
+        assert_eq!(alpn_protocol, None);
+        assert_eq!(response, "http/1.1 handler");
+
+        Ok(())
+    })
+}
@@ -16,7 +16,7 @@ mod inner_helper {
     use futures_util::io::{AsyncReadExt, AsyncWriteExt};
     use rustls::ClientSession;
 
-    use async_tls_lite::client_handshake;
+    use async_tls_lite::{client_handshake, Eof};
 
     pub async fn run_async_client(
         client_session: ClientSession,
@@ -27,10 +27,11 @@ mod inner_helper {
 
         let mut tls_stream = client_handshake(client_session, tcp_stream).await?;
 
-        tls_stream.write(b"foo").await?;
+        tls_stream.write_all(b"foo").await?;
         println!("client tls_stream write foo done");
         let mut buf = [0; 5];
-        tls_stream.read(&mut buf).await?;
+        let n = tls_stream.read(&mut buf).await?;
+        assert_eq!(n, 3);
         assert_eq!(&buf, b"bar\0\0");
         println!("client tls_stream read bar done");
 
@@ -40,7 +41,73 @@ mod inner_helper {
         println!("client tls_stream shutdown done");
         sender
             .send("client_done".to_owned())
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            .map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e))?;
+
+        Ok(())
+    }
+
+    pub async fn run_async_client_with_close(
+        client_session: ClientSession,
+        tcp_stream: TcpStream,
+        sender: Sender<String>,
+    ) -> io::Result<()> {
+        let tcp_stream = Async::<TcpStream>::new(tcp_stream)?;
+
+        let mut tls_stream = client_handshake(client_session, tcp_stream).await?;
+
+        tls_stream.write_all(b"foo").await?;
+        println!("client tls_stream write foo done");
+        let mut buf = [0; 5];
+        let n = tls_stream.read(&mut buf).await?;
+        assert_eq!(n, 3);
+        assert_eq!(&buf, b"bar\0\0");
+        println!("client tls_stream read bar done");
+
+        tls_stream.close().await?;
+        println!("client tls_stream close done");
+
+        sender
+            .send("client_done".to_owned())
+            .map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e))?;
+
+        Ok(())
+    }
+
+    pub async fn run_async_client_with_shutdown_write(
+        client_session: ClientSession,
+        tcp_stream: TcpStream,
+        sender: Sender<String>,
+    ) -> io::Result<()> {
+        let tcp_stream = Async::<TcpStream>::new(tcp_stream)?;
+
+        let mut tls_stream = client_handshake(client_session, tcp_stream).await?;
+
+        tls_stream.write_all(b"foo").await?;
+        println!("client tls_stream write foo done");
+
+        tls_stream.shutdown_write().await?;
+        assert!(tls_stream.is_write_shutdown());
+        assert!(!tls_stream.is_read_shutdown());
+        let err = tls_stream.write_all(b"foo").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        println!("client tls_stream shutdown_write done");
+
+        let mut buf = [0; 5];
+        let n = tls_stream.read(&mut buf).await?;
+        assert_eq!(n, 3);
+        assert_eq!(&buf, b"bar\0\0");
+        println!("client tls_stream read bar done");
+
+        let mut buf = [0; 5];
+        let n = tls_stream.read(&mut buf).await?;
+        assert_eq!(n, 0);
+        assert!(tls_stream.is_read_shutdown());
+        assert_eq!(tls_stream.eof(), Some(Eof::CloseNotify));
+        println!("client tls_stream read close_notify done");
+
+        sender
+            .send("client_done".to_owned())
+            .map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e))?;
 
         Ok(())
     }
@@ -69,7 +136,7 @@ fn tcp_stream() -> io::Result<()> {
         let (sender_s, receiver) = mpsc::channel::<String>();
         let sender_c = sender_s.clone();
 
-        let executor = ThreadPool::new()?;
+        let executor = ThreadPool::builder().pool_size(2).create()?;
 
         executor
             .spawn(async move {
@@ -81,7 +148,7 @@ fn tcp_stream() -> io::Result<()> {
                     })
                     .unwrap()
             })
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            .expect("failed to spawn");
 
         executor
             .spawn(async move {
@@ -93,7 +160,7 @@ fn tcp_stream() -> io::Result<()> {
                     })
                     .unwrap()
             })
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            .expect("failed to spawn");
 
         let msg = receiver.recv().unwrap();
         println!("receiver.recv {}", msg);
@@ -106,3 +173,118 @@ fn tcp_stream() -> io::Result<()> {
         Ok(())
     })
 }
+
+#[test]
+fn close_notify() -> io::Result<()> {
+    block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+
+        let client_session = helper::get_client_session()?;
+        let mut server_session = helper::get_server_session()?;
+
+        let tcp_stream_c = TcpStream::connect(addr)?;
+        let mut tcp_stream_s = listener
+            .incoming()
+            .next()
+            .expect("Get next incoming failed")?;
+
+        let (sender_s, receiver) = mpsc::channel::<String>();
+        let sender_c = sender_s.clone();
+
+        let executor = ThreadPool::builder().pool_size(2).create()?;
+
+        executor
+            .spawn(async move {
+                helper::run_sync_server_until_close_notify(
+                    &mut server_session,
+                    &mut tcp_stream_s,
+                    sender_s,
+                )
+                .await
+                .map_err(|err| {
+                    eprintln!("run_sync_server_until_close_notify failed, err: {:?}", err);
+                    err
+                })
+                .unwrap()
+            })
+            .expect("failed to spawn");
+
+        executor
+            .spawn(async move {
+                inner_helper::run_async_client_with_close(client_session, tcp_stream_c, sender_c)
+                    .await
+                    .map_err(|err| {
+                        eprintln!("run_async_client_with_close failed, err: {:?}", err);
+                        err
+                    })
+                    .unwrap()
+            })
+            .expect("failed to spawn");
+
+        let mut msgs = vec![receiver.recv().unwrap(), receiver.recv().unwrap()];
+        msgs.sort();
+        assert_eq!(msgs, vec!["client_done", "server_done"]);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn shutdown_write() -> io::Result<()> {
+    block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+
+        let client_session = helper::get_client_session()?;
+        let mut server_session = helper::get_server_session()?;
+
+        let tcp_stream_c = TcpStream::connect(addr)?;
+        let mut tcp_stream_s = listener
+            .incoming()
+            .next()
+            .expect("Get next incoming failed")?;
+
+        let (sender_s, receiver) = mpsc::channel::<String>();
+        let sender_c = sender_s.clone();
+
+        let executor = ThreadPool::builder().pool_size(2).create()?;
+
+        executor
+            .spawn(async move {
+                helper::run_sync_server_half_close(&mut server_session, &mut tcp_stream_s, sender_s)
+                    .await
+                    .map_err(|err| {
+                        eprintln!("run_sync_server_half_close failed, err: {:?}", err);
+                        err
+                    })
+                    .unwrap()
+            })
+            .expect("failed to spawn");
+
+        executor
+            .spawn(async move {
+                inner_helper::run_async_client_with_shutdown_write(
+                    client_session,
+                    tcp_stream_c,
+                    sender_c,
+                )
+                .await
+                .map_err(|err| {
+                    eprintln!(
+                        "run_async_client_with_shutdown_write failed, err: {:?}",
+                        err
+                    );
+                    err
+                })
+                .unwrap()
+            })
+            .expect("failed to spawn");
+
+        let mut msgs = vec![receiver.recv().unwrap(), receiver.recv().unwrap()];
+        msgs.sort();
+        assert_eq!(msgs, vec!["client_done", "server_done"]);
+
+        Ok(())
+    })
+}
@@ -16,7 +16,7 @@ mod inner_helper {
     use futures_util::io::{AsyncReadExt, AsyncWriteExt};
     use rustls::ServerSession;
 
-    use async_tls_lite::server_handshake;
+    use async_tls_lite::{server_handshake, Eof, TruncationPolicy};
 
     pub async fn run_async_server(
         server_session: ServerSession,
@@ -28,11 +28,12 @@ mod inner_helper {
         let mut tls_stream = server_handshake(server_session, tcp_stream).await?;
 
         let mut buf = [0; 5];
-        tls_stream.read(&mut buf).await?;
+        let n = tls_stream.read(&mut buf).await?;
+        assert_eq!(n, 3);
         assert_eq!(&buf, b"foo\0\0");
         println!("server tls_stream read foo done");
 
-        tls_stream.write(b"bar").await?;
+        tls_stream.write_all(b"bar").await?;
         println!("server tls_stream write bar done");
 
         let mut buf = [0; 5];
@@ -43,7 +44,67 @@ mod inner_helper {
 
         sender
             .send("server_done".to_owned())
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            .map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e))?;
+
+        Ok(())
+    }
+
+    pub async fn run_async_server_with_strict_truncation_policy(
+        server_session: ServerSession,
+        tcp_stream: TcpStream,
+        sender: Sender<String>,
+    ) -> io::Result<()> {
+        let tcp_stream = Async::<TcpStream>::new(tcp_stream)?;
+
+        let mut tls_stream = server_handshake(server_session, tcp_stream).await?;
+        tls_stream.set_truncation_policy(TruncationPolicy::Strict);
+
+        let mut buf = [0; 5];
+        let n = tls_stream.read(&mut buf).await?;
+        assert_eq!(n, 3);
+        assert_eq!(&buf, b"foo\0\0");
+        println!("server tls_stream read foo done");
+
+        tls_stream.write_all(b"bar").await?;
+        println!("server tls_stream write bar done");
+
+        let mut buf = [0; 5];
+        let err = tls_stream.read(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(tls_stream.eof(), Some(Eof::Truncated));
+        println!("server tls_stream read truncated EOF done");
+
+        sender
+            .send("server_done".to_owned())
+            .map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e))?;
+
+        Ok(())
+    }
+
+    pub async fn run_async_server_with_close(
+        server_session: ServerSession,
+        tcp_stream: TcpStream,
+        sender: Sender<String>,
+    ) -> io::Result<()> {
+        let tcp_stream = Async::<TcpStream>::new(tcp_stream)?;
+
+        let mut tls_stream = server_handshake(server_session, tcp_stream).await?;
+
+        let mut buf = [0; 5];
+        let n = tls_stream.read(&mut buf).await?;
+        assert_eq!(n, 3);
+        assert_eq!(&buf, b"foo\0\0");
+        println!("server tls_stream read foo done");
+
+        tls_stream.write_all(b"bar").await?;
+        println!("server tls_stream write bar done");
+
+        tls_stream.close().await?;
+        println!("server tls_stream close done");
+
+        sender
+            .send("server_done".to_owned())
+            .map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e))?;
 
         Ok(())
     }
@@ -72,7 +133,7 @@ fn tcp_stream() -> io::Result<()> {
         let (sender_s, receiver) = mpsc::channel::<String>();
         let sender_c = sender_s.clone();
 
-        let executor = ThreadPool::new()?;
+        let executor = ThreadPool::builder().pool_size(2).create()?;
 
         executor
             .spawn(async move {
@@ -84,7 +145,7 @@ fn tcp_stream() -> io::Result<()> {
                     })
                     .unwrap()
             })
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            .expect("failed to spawn");
 
         executor
             .spawn(async move {
@@ -96,15 +157,128 @@ fn tcp_stream() -> io::Result<()> {
                     })
                     .unwrap()
             })
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            .expect("failed to spawn");
+
+        // The client reports after its shutdown, which the server may see first.
+        let mut msgs = vec![receiver.recv().unwrap(), receiver.recv().unwrap()];
+        println!("receiver.recv {:?}", msgs);
+        msgs.sort();
+        assert_eq!(msgs, vec!["client_done", "server_done"]);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn close_notify() -> io::Result<()> {
+    block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+
+        let mut client_session = helper::get_client_session()?;
+        let server_session = helper::get_server_session()?;
+
+        let mut tcp_stream_c = TcpStream::connect(addr)?;
+        let tcp_stream_s = listener
+            .incoming()
+            .next()
+            .expect("Get next incoming failed")?;
+
+        let (sender_s, receiver) = mpsc::channel::<String>();
+        let sender_c = sender_s.clone();
+
+        let executor = ThreadPool::builder().pool_size(2).create()?;
+
+        executor
+            .spawn(async move {
+                inner_helper::run_async_server_with_close(server_session, tcp_stream_s, sender_s)
+                    .await
+                    .map_err(|err| {
+                        eprintln!("run_async_server_with_close failed, err: {:?}", err);
+                        err
+                    })
+                    .unwrap()
+            })
+            .expect("failed to spawn");
+
+        executor
+            .spawn(async move {
+                helper::run_sync_client_until_close_notify(
+                    &mut client_session,
+                    &mut tcp_stream_c,
+                    sender_c,
+                )
+                .await
+                .map_err(|err| {
+                    eprintln!("run_sync_client_until_close_notify failed, err: {:?}", err);
+                    err
+                })
+                .unwrap()
+            })
+            .expect("failed to spawn");
+
+        let mut msgs = vec![receiver.recv().unwrap(), receiver.recv().unwrap()];
+        msgs.sort();
+        assert_eq!(msgs, vec!["client_done", "server_done"]);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn truncation_policy_strict() -> io::Result<()> {
+    block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+
+        let mut client_session = helper::get_client_session()?;
+        let server_session = helper::get_server_session()?;
+
+        let mut tcp_stream_c = TcpStream::connect(addr)?;
+        let tcp_stream_s = listener
+            .incoming()
+            .next()
+            .expect("Get next incoming failed")?;
+
+        let (sender_s, receiver) = mpsc::channel::<String>();
+        let sender_c = sender_s.clone();
+
+        let executor = ThreadPool::builder().pool_size(2).create()?;
+
+        executor
+            .spawn(async move {
+                inner_helper::run_async_server_with_strict_truncation_policy(
+                    server_session,
+                    tcp_stream_s,
+                    sender_s,
+                )
+                .await
+                .map_err(|err| {
+                    eprintln!(
+                        "run_async_server_with_strict_truncation_policy failed, err: {:?}",
+                        err
+                    );
+                    err
+                })
+                .unwrap()
+            })
+            .expect("failed to spawn");
 
-        let msg = receiver.recv().unwrap();
-        println!("receiver.recv {}", msg);
-        assert_eq!(msg, "client_done");
+        executor
+            .spawn(async move {
+                helper::run_sync_client(&mut client_session, &mut tcp_stream_c, sender_c)
+                    .await
+                    .map_err(|err| {
+                        eprintln!("run_sync_client failed, err: {:?}", err);
+                        err
+                    })
+                    .unwrap()
+            })
+            .expect("failed to spawn");
 
-        let msg = receiver.recv().unwrap();
-        println!("receiver.recv {}", msg);
-        assert_eq!(msg, "server_done");
+        let mut msgs = vec![receiver.recv().unwrap(), receiver.recv().unwrap()];
+        msgs.sort();
+        assert_eq!(msgs, vec!["client_done", "server_done"]);
 
         Ok(())
     })
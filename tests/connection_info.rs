@@ -0,0 +1,81 @@
+#![cfg(all(feature = "acceptor", feature = "connector"))]
+
+use std::io;
+use std::sync::Arc;
+
+use futures_executor::block_on;
+
+use async_tls_lite::{TlsAcceptor, TlsConnector};
+use rustls::ciphersuite::TLS13_AES_128_GCM_SHA256;
+use rustls::{CipherSuite, ProtocolVersion};
+
+mod helper;
+
+#[test]
+fn negotiated_parameters() -> io::Result<()> {
+    block_on(async {
+        let mut client_config = helper::get_client_config()?;
+        client_config.alpn_protocols = vec![b"h2".to_vec()];
+        client_config.ciphersuites = vec![&TLS13_AES_128_GCM_SHA256];
+        let mut server_config = helper::get_server_config()?;
+        server_config.set_protocols(&[b"h2".to_vec(), b"http/1.1".to_vec()]);
+
+        let acceptor = TlsAcceptor::from(Arc::new(server_config));
+        let connector = TlsConnector::from(Arc::new(client_config));
+        let (server_info, client_info) =
+            helper::connection_infos(&acceptor, &connector, "tls.lvh.me").await?;
+
+        for info in &[&client_info, &server_info] {
+            assert_eq!(info.protocol_version, Some(ProtocolVersion::TLSv1_3));
+            assert_eq!(
+                info.cipher_suite,
+                Some(CipherSuite::TLS13_AES_128_GCM_SHA256)
+            );
+            assert_eq!(
+                info.cipher_suite_name().as_deref(),
+                Some("TLS_AES_128_GCM_SHA256")
+            );
+            assert_eq!(info.alpn_protocol, Some(b"h2".to_vec()));
+            assert!(!info.resumed);
+        }
+
+        assert_eq!(client_info.sni_hostname, None);
+        assert_eq!(server_info.sni_hostname, Some("tls.lvh.me".to_owned()));
+
+        assert!(!client_info.peer_certificates.unwrap().is_empty());
+        assert!(server_info.peer_certificates.is_none());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn resumed() -> io::Result<()> {
+    for versions in &[
+        vec![ProtocolVersion::TLSv1_3],
+        vec![ProtocolVersion::TLSv1_2],
+    ] {
+        block_on(async {
+            let mut client_config = helper::get_client_config()?;
+            client_config.versions = versions.clone();
+            let acceptor = TlsAcceptor::from(Arc::new(helper::get_server_config()?));
+            let connector = TlsConnector::from(Arc::new(client_config));
+
+            let (server_info, client_info) =
+                helper::connection_infos(&acceptor, &connector, "tls.lvh.me").await?;
+            assert_eq!(client_info.protocol_version, Some(versions[0]));
+            assert!(!client_info.resumed);
+            assert!(!server_info.resumed);
+
+            let (server_info, client_info) =
+                helper::connection_infos(&acceptor, &connector, "tls.lvh.me").await?;
+            assert_eq!(client_info.protocol_version, Some(versions[0]));
+            assert!(client_info.resumed);
+            assert!(server_info.resumed);
+
+            Ok::<_, io::Error>(())
+        })?;
+    }
+
+    Ok(())
+}
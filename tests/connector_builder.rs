@@ -0,0 +1,82 @@
+#![cfg(all(feature = "acceptor", feature = "connector"))]
+
+use std::env;
+use std::fs;
+use std::io;
+use std::sync::Arc;
+
+use futures_executor::block_on;
+
+use async_tls_lite::{TlsAcceptor, TlsConnector, TlsConnectorBuilder};
+
+mod helper;
+
+fn builder_without_roots() -> TlsConnectorBuilder {
+    let builder = TlsConnector::builder().webpki_roots(false);
+    #[cfg(feature = "native-roots")]
+    let builder = builder.native_roots(false);
+    builder
+}
+
+fn acceptor() -> io::Result<TlsAcceptor> {
+    let mut server_config = helper::get_server_config()?;
+    server_config.set_protocols(&[b"h2".to_vec(), b"http/1.1".to_vec()]);
+    Ok(TlsAcceptor::from(Arc::new(server_config)))
+}
+
+#[test]
+fn root_cert_pem_file() -> io::Result<()> {
+    block_on(async {
+        let connector = builder_without_roots()
+            .root_cert_pem_file(helper::get_mkcert_path().join("rootCA.pem"))
+            .alpn_protocols(vec![b"http/1.1".to_vec()])
+            .build()?;
+
+        let (_, info) = helper::connection_infos(&acceptor()?, &connector, "tls.lvh.me").await?;
+        assert_eq!(info.alpn_protocol, Some(b"http/1.1".to_vec()));
+
+        let connector = TlsConnector::builder().build()?;
+        let (_, client) = helper::connect_pair(&acceptor()?, &connector, "tls.lvh.me").await;
+        let err = client.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn root_cert_pem_dir() -> io::Result<()> {
+    block_on(async {
+        let dir = env::temp_dir().join(format!("async-tls-lite-roots-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        fs::copy(
+            helper::get_mkcert_path().join("rootCA.pem"),
+            dir.join("rootCA.pem"),
+        )?;
+        fs::write(dir.join("README.md"), "not a cert")?;
+
+        let connector = builder_without_roots().root_cert_pem_dir(&dir).build()?;
+        fs::remove_dir_all(&dir)?;
+
+        let (_, info) = helper::connection_infos(&acceptor()?, &connector, "tls.lvh.me").await?;
+        assert_eq!(info.alpn_protocol, None);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn build_errors() -> io::Result<()> {
+    let err = builder_without_roots().build().err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    let err = TlsConnector::builder()
+        .root_cert_pem_file(helper::get_mkcert_path().join("tls.lvh.me-key.pem"))
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(err.to_string().contains("tls.lvh.me-key.pem"));
+
+    Ok(())
+}
@@ -0,0 +1,97 @@
+#![cfg(all(feature = "dangerous", feature = "acceptor"))]
+
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use futures_executor::block_on;
+use log::{Level, Log, Metadata, Record};
+
+use async_tls_lite::{Error, TlsAcceptor, TlsConnector, TlsConnectorBuilder};
+
+mod helper;
+
+struct Logger(Mutex<Vec<String>>);
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Warn
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.0.lock().unwrap().push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: Logger = Logger(Mutex::new(vec![]));
+
+fn builder_without_roots() -> TlsConnectorBuilder {
+    let builder = TlsConnector::builder().webpki_roots(false);
+    #[cfg(feature = "native-roots")]
+    let builder = builder.native_roots(false);
+    builder
+}
+
+async fn connect(connector: &TlsConnector, domain: &str) -> io::Result<()> {
+    let acceptor = TlsAcceptor::from(Arc::new(helper::get_server_config()?));
+    helper::connect_pair(&acceptor, connector, domain).await.1?;
+    Ok(())
+}
+
+#[test]
+fn danger() -> io::Result<()> {
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(log::LevelFilter::Warn);
+
+    block_on(async {
+        // Untrusted CA and wrong hostname.
+        let connector = builder_without_roots()
+            .danger_accept_invalid_certs(true)
+            .build()?;
+        connect(&connector, "tls.lvh.me").await?;
+        connect(&connector, "a.lvh.me").await?;
+
+        // Wrong hostname only.
+        let connector = builder_without_roots()
+            .root_cert_pem_file(helper::get_mkcert_path().join("rootCA.pem"))
+            .danger_accept_invalid_hostnames(true)
+            .build()?;
+        connect(&connector, "a.lvh.me").await?;
+
+        let connector = builder_without_roots()
+            .root_cert_pem_file(helper::get_mkcert_path().join("a.lvh.me.pem"))
+            .danger_accept_invalid_hostnames(true)
+            .build()?;
+        assert!(connect(&connector, "tls.lvh.me").await.is_err());
+
+        assert_eq!(
+            LOGGER.0.lock().unwrap().clone(),
+            vec![
+                "connecting to tls.lvh.me with invalid certs accepted",
+                "connecting to a.lvh.me with invalid certs accepted",
+                "connecting to a.lvh.me with invalid hostnames accepted",
+                "connecting to tls.lvh.me with invalid hostnames accepted",
+            ]
+        );
+
+        // Pins still apply.
+        let connector = builder_without_roots()
+            .danger_accept_invalid_certs(true)
+            .pin_spki_sha256("A0TV1pYF8vF4Y8+mIOxBuMnQMNdcLWUHTk0VLsgaPaU=")
+            .build()?;
+        let err = connect(&connector, "tls.lvh.me").await.err().unwrap();
+        assert!(matches!(
+            err.get_ref().unwrap().downcast_ref::<Error>(),
+            Some(Error::PinMismatch)
+        ));
+
+        // Off by default.
+        let connector = builder_without_roots().build();
+        assert!(connector.is_err());
+
+        Ok(())
+    })
+}
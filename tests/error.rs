@@ -0,0 +1,126 @@
+#![cfg(all(feature = "acceptor", feature = "connector"))]
+
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_io::Timer;
+use futures_executor::block_on;
+
+use async_tls_lite::prelude::{AlertDescription, ClientConfig, ProtocolVersion, TLSError};
+use async_tls_lite::{Error, TlsAcceptor, TlsConnector};
+
+mod helper;
+
+fn typed(err: &io::Error) -> &Error {
+    err.get_ref().unwrap().downcast_ref::<Error>().unwrap()
+}
+
+#[test]
+fn invalid_server_name() -> io::Result<()> {
+    block_on(async {
+        let connector = TlsConnector::from(Arc::new(helper::get_client_config()?));
+        let (stream_c, _stream_s) = helper::duplex();
+
+        let err = connector
+            .connect("not a name", stream_c)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(matches!(typed(&err), Error::InvalidServerName));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn certificate_invalid_and_peer_alert() -> io::Result<()> {
+    block_on(async {
+        let acceptor = TlsAcceptor::from(Arc::new(helper::get_server_config()?));
+        // No roots. Over TLS 1.3 the client's alert would go out before it has keys and
+        // the server would drop it.
+        let mut config = ClientConfig::new();
+        config.versions = vec![ProtocolVersion::TLSv1_2];
+        let connector = TlsConnector::from(Arc::new(config));
+
+        let (server, client) = helper::connect_pair(&acceptor, &connector, "tls.lvh.me").await;
+
+        let err = client.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(matches!(typed(&err), Error::CertificateInvalid { .. }));
+
+        let err = server.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(matches!(
+            typed(&err),
+            Error::PeerAlert(AlertDescription::BadCertificate)
+        ));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn handshake_timeout() -> io::Result<()> {
+    block_on(async {
+        let acceptor = TlsAcceptor::from(Arc::new(helper::get_server_config()?))
+            .with_handshake_timeout(Duration::from_millis(100), Timer::new);
+        let (stream_s, _stream_c) = helper::duplex();
+
+        let err = acceptor.accept(stream_s).await.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(matches!(Error::from(err), Error::HandshakeTimeout));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn io() -> io::Result<()> {
+    block_on(async {
+        let acceptor = TlsAcceptor::from(Arc::new(helper::get_server_config()?));
+        let (stream_s, stream_c) = helper::duplex();
+        drop(stream_c);
+
+        let err = acceptor.accept(stream_s).await.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        match Error::from(err) {
+            Error::Io(err) => assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof),
+            err => panic!("{:?}", err),
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn conversions() {
+    let err = io::Error::from(Error::Io(io::Error::new(io::ErrorKind::BrokenPipe, "foo")));
+    assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    assert_eq!(err.to_string(), "foo");
+
+    let err = io::Error::from(Error::PinMismatch);
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(matches!(Error::from(err), Error::PinMismatch));
+
+    // A `TLSError` from the sync API is typed too.
+    let err = io::Error::new(
+        io::ErrorKind::InvalidData,
+        TLSError::AlertReceived(AlertDescription::HandshakeFailure),
+    );
+    assert!(matches!(
+        Error::from(err),
+        Error::PeerAlert(AlertDescription::HandshakeFailure)
+    ));
+
+    // Matched by its fixed message, other general errors stay as they are.
+    let err = Error::from(TLSError::General("certificate pin mismatch".to_owned()));
+    assert!(matches!(err, Error::PinMismatch));
+    let err = Error::from(TLSError::General("foo".to_owned()));
+    assert!(matches!(err, Error::Tls(TLSError::General(_))));
+
+    let err = Error::from(TLSError::DecryptError);
+    assert!(matches!(err, Error::Tls(TLSError::DecryptError)));
+    assert_eq!(err.to_string(), TLSError::DecryptError.to_string());
+}
@@ -0,0 +1,58 @@
+#![cfg(all(feature = "acceptor", feature = "connector"))]
+
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_io::Timer;
+use futures_executor::block_on;
+use futures_util::future::FusedFuture;
+
+use async_tls_lite::{TlsAcceptor, TlsConnector};
+
+mod helper;
+
+#[test]
+fn accept_polled_after_completion() -> io::Result<()> {
+    block_on(async {
+        let (stream_s, _stream_c) = helper::duplex();
+
+        let acceptor = TlsAcceptor::from(Arc::new(helper::get_server_config()?))
+            .with_handshake_timeout(Duration::from_millis(50), Timer::new);
+
+        let mut accept = acceptor.accept(stream_s);
+        assert!(!accept.is_terminated());
+
+        let err = (&mut accept).await.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(accept.is_terminated());
+
+        let err = (&mut accept).await.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+        assert!(accept.is_terminated());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn connect_polled_after_completion() -> io::Result<()> {
+    block_on(async {
+        let (stream_c, _stream_s) = helper::duplex();
+
+        let connector = TlsConnector::from(Arc::new(helper::get_client_config()?));
+
+        let mut connect = connector.connect("invalid domain", stream_c);
+        assert!(!connect.is_terminated());
+
+        let err = (&mut connect).await.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(connect.is_terminated());
+
+        let err = (&mut connect).await.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+        assert!(connect.is_terminated());
+
+        Ok(())
+    })
+}
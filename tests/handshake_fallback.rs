@@ -0,0 +1,96 @@
+#![cfg(all(feature = "acceptor", feature = "connector"))]
+
+use std::io;
+use std::sync::Arc;
+
+use futures_executor::block_on;
+use futures_util::future::join;
+use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+
+use async_tls_lite::{TlsAcceptor, TlsConnector};
+
+mod helper;
+
+#[test]
+fn accept_plaintext_http_request() -> io::Result<()> {
+    block_on(async {
+        let (stream_s, mut stream_c) = helper::duplex();
+
+        let acceptor = TlsAcceptor::from(Arc::new(helper::get_server_config()?));
+
+        let server = async move {
+            let err = acceptor.accept_with_fallback(stream_s).await.err().unwrap();
+            assert_eq!(err.error.kind(), io::ErrorKind::InvalidData);
+            assert!(err.read_bytes.starts_with(b"GET / HTTP/1.1\r\n"));
+            println!("server accept_with_fallback failed, err: {}", err);
+
+            let mut stream = err.stream;
+            stream
+                .write_all(b"HTTP/1.1 400 Bad Request\r\n\r\n")
+                .await?;
+            stream.close().await?;
+
+            Ok::<_, io::Error>(())
+        };
+
+        let client = async move {
+            stream_c.write_all(b"GET / HTTP/1.1\r\n\r\n").await?;
+
+            let mut buf = vec![];
+            stream_c.read_to_end(&mut buf).await?;
+            assert!(buf.ends_with(b"HTTP/1.1 400 Bad Request\r\n\r\n"));
+
+            Ok::<_, io::Error>(())
+        };
+
+        let (ret_s, ret_c) = join(server, client).await;
+        ret_s?;
+        ret_c?;
+
+        Ok(())
+    })
+}
+
+#[test]
+fn connect_to_plaintext_server() -> io::Result<()> {
+    block_on(async {
+        let (stream_c, mut stream_s) = helper::duplex();
+
+        let connector = TlsConnector::from(Arc::new(helper::get_client_config()?));
+
+        let server = async move {
+            let mut buf = [0; 5];
+            stream_s.read_exact(&mut buf).await?;
+
+            stream_s.write_all(b"220 smtp.lvh.me ESMTP\r\n").await?;
+
+            let mut buf = vec![];
+            stream_s.read_to_end(&mut buf).await?;
+            assert!(buf.ends_with(b"QUIT\r\n"));
+
+            Ok::<_, io::Error>(())
+        };
+
+        let client = async move {
+            let err = connector
+                .connect_with_fallback("tls.lvh.me", stream_c)
+                .await
+                .err()
+                .unwrap();
+            assert_eq!(err.error.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(err.read_bytes, b"220 smtp.lvh.me ESMTP\r\n");
+
+            let mut stream = err.stream;
+            stream.write_all(b"QUIT\r\n").await?;
+            stream.close().await?;
+
+            Ok::<_, io::Error>(())
+        };
+
+        let (ret_s, ret_c) = join(server, client).await;
+        ret_s?;
+        ret_c?;
+
+        Ok(())
+    })
+}
@@ -0,0 +1,50 @@
+#![cfg(all(feature = "acceptor", feature = "connector"))]
+
+use std::io;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_io::Timer;
+use futures_executor::block_on;
+
+use async_tls_lite::{TlsAcceptor, TlsConnector};
+
+mod helper;
+
+#[test]
+fn accept_with_stalled_client() -> io::Result<()> {
+    block_on(async {
+        let (stream_s, _stream_c) = helper::duplex();
+
+        let acceptor = TlsAcceptor::from(Arc::new(helper::get_server_config()?))
+            .with_handshake_timeout(Duration::from_millis(100), Timer::new);
+
+        let instant = Instant::now();
+        let err = acceptor.accept(stream_s).await.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(instant.elapsed() >= Duration::from_millis(100));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn connect_with_stalled_server() -> io::Result<()> {
+    block_on(async {
+        let (stream_c, _stream_s) = helper::duplex();
+
+        let connector = TlsConnector::from(Arc::new(helper::get_client_config()?))
+            .with_handshake_timeout(Duration::from_millis(100), Timer::new);
+
+        let instant = Instant::now();
+        let err = connector
+            .connect("tls.lvh.me", stream_c)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(instant.elapsed() >= Duration::from_millis(100));
+
+        Ok(())
+    })
+}
@@ -1,20 +1,35 @@
+use std::collections::VecDeque;
 use std::fs::File;
 use std::io::{self, BufReader, Read, Write};
 use std::net::{Shutdown, TcpStream};
 use std::path::PathBuf;
+use std::pin::Pin;
 use std::sync::mpsc::Sender;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
 use std::thread;
 use std::time::Duration;
 
+#[cfg(all(feature = "acceptor", feature = "connector"))]
+use futures_util::future::join;
+use futures_util::io::{AsyncRead, AsyncWrite};
+#[cfg(all(feature = "acceptor", feature = "connector"))]
+use futures_util::io::{AsyncReadExt, AsyncWriteExt};
 use rustls::{
-    internal::pemfile, ClientConfig, ClientSession, NoClientAuth, ServerConfig, ServerSession,
-    Session, Stream,
+    internal::pemfile, Certificate, ClientConfig, ClientSession, NoClientAuth, PrivateKey,
+    ServerConfig, ServerSession, Session, Stream,
 };
 use webpki::DNSNameRef;
 use webpki_roots::TLS_SERVER_ROOTS;
 
-pub fn get_client_session() -> io::Result<ClientSession> {
+#[cfg(all(feature = "acceptor", feature = "connector"))]
+use async_tls_lite::{
+    ConnectionInfo, IntoServerName, TlsAcceptor, TlsAcceptorBuilder, TlsConnector,
+    TlsConnectorBuilder, TlsStream,
+};
+
+#[allow(dead_code)]
+pub fn get_client_config() -> io::Result<ClientConfig> {
     let mut client_config = ClientConfig::new();
 
     let mkcert_path = get_mkcert_path();
@@ -28,34 +43,58 @@ pub fn get_client_session() -> io::Result<ClientSession> {
         )?))
         .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid cert"))?;
 
+    Ok(client_config)
+}
+
+#[allow(dead_code)]
+pub fn get_client_session() -> io::Result<ClientSession> {
     let client_session = ClientSession::new(
-        &Arc::new(client_config),
+        &Arc::new(get_client_config()?),
         DNSNameRef::try_from_ascii_str("tls.lvh.me").unwrap(),
     );
 
     Ok(client_session)
 }
 
-pub fn get_server_session() -> io::Result<ServerSession> {
+#[allow(dead_code)]
+pub fn get_server_config() -> io::Result<ServerConfig> {
     let mut server_config = ServerConfig::new(NoClientAuth::new());
 
+    let (certs, key) = get_cert_and_key("tls.lvh.me")?;
+    server_config
+        .set_single_cert(certs, key)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+    Ok(server_config)
+}
+
+/// `name` is the mkcert file name, e.g. `tls.lvh.me` or `_wildcard.wild.lvh.me`.
+#[allow(dead_code)]
+pub fn get_cert_and_key(name: &str) -> io::Result<(Vec<Certificate>, PrivateKey)> {
     let mkcert_path = get_mkcert_path();
-    let certs = pemfile::certs(&mut BufReader::new(File::open(
-        mkcert_path.join("tls.lvh.me.crt"),
-    )?))
-    .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid cert"))?;
+    let certs = get_certs(name)?;
     let key = pemfile::pkcs8_private_keys(&mut BufReader::new(File::open(
-        mkcert_path.join("tls.lvh.me-key.pem"),
+        mkcert_path.join(format!("{}-key.pem", name)),
     )?))
     .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid key"))?
     .first()
     .expect("invalid key")
     .to_owned();
-    server_config
-        .set_single_cert(certs, key)
-        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
 
-    let server_session = ServerSession::new(&Arc::new(server_config));
+    Ok((certs, key))
+}
+
+#[allow(dead_code)]
+pub fn get_certs(name: &str) -> io::Result<Vec<Certificate>> {
+    pemfile::certs(&mut BufReader::new(File::open(
+        get_mkcert_path().join(format!("{}.crt", name)),
+    )?))
+    .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid cert"))
+}
+
+#[allow(dead_code)]
+pub fn get_server_session() -> io::Result<ServerSession> {
+    let server_session = ServerSession::new(&Arc::new(get_server_config()?));
 
     Ok(server_session)
 }
@@ -66,18 +105,17 @@ pub async fn run_sync_server(
     tcp_stream: &mut TcpStream,
     sender: Sender<String>,
 ) -> io::Result<()> {
-    server_session
-        .complete_io(tcp_stream)
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    server_session.complete_io(tcp_stream)?;
 
     let mut tls_stream = Stream::new(server_session, tcp_stream);
 
     let mut buf = [0; 5];
-    tls_stream.read(&mut buf)?;
+    let n = tls_stream.read(&mut buf)?;
+    assert_eq!(n, 3);
     assert_eq!(&buf, b"foo\0\0");
     println!("server tls_stream read foo done");
 
-    tls_stream.write(b"bar")?;
+    tls_stream.write_all(b"bar")?;
     println!("server tls_stream write bar done");
 
     thread::sleep(Duration::from_millis(200));
@@ -90,7 +128,7 @@ pub async fn run_sync_server(
 
     sender
         .send("server_done".to_owned())
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        .map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e))?;
 
     Ok(())
 }
@@ -101,17 +139,16 @@ pub async fn run_sync_client(
     tcp_stream: &mut TcpStream,
     sender: Sender<String>,
 ) -> io::Result<()> {
-    client_session
-        .complete_io(tcp_stream)
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    client_session.complete_io(tcp_stream)?;
 
     let mut tls_stream = Stream::new(client_session, tcp_stream);
 
-    tls_stream.write(b"foo")?;
+    tls_stream.write_all(b"foo")?;
     println!("client tls_stream write foo done");
 
     let mut buf = [0; 5];
-    tls_stream.read(&mut buf)?;
+    let n = tls_stream.read(&mut buf)?;
+    assert_eq!(n, 3);
     assert_eq!(&buf, b"bar\0\0");
     println!("client tls_stream read bar done");
 
@@ -120,11 +157,323 @@ pub async fn run_sync_client(
 
     sender
         .send("client_done".to_owned())
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        .map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e))?;
 
     Ok(())
 }
 
-fn get_mkcert_path() -> PathBuf {
+#[allow(dead_code)]
+pub async fn run_sync_server_until_close_notify(
+    server_session: &mut ServerSession,
+    tcp_stream: &mut TcpStream,
+    sender: Sender<String>,
+) -> io::Result<()> {
+    server_session.complete_io(tcp_stream)?;
+
+    let mut tls_stream = Stream::new(server_session, tcp_stream);
+
+    let mut buf = [0; 5];
+    let n = tls_stream.read(&mut buf)?;
+    assert_eq!(n, 3);
+    assert_eq!(&buf, b"foo\0\0");
+    println!("server tls_stream read foo done");
+
+    tls_stream.write_all(b"bar")?;
+    println!("server tls_stream write bar done");
+
+    let mut buf = [0; 5];
+    let err = tls_stream.read(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionAborted);
+    println!("server tls_stream read close_notify done");
+
+    sender
+        .send("server_done".to_owned())
+        .map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e))?;
+
+    Ok(())
+}
+
+#[allow(dead_code)]
+pub async fn run_sync_client_until_close_notify(
+    client_session: &mut ClientSession,
+    tcp_stream: &mut TcpStream,
+    sender: Sender<String>,
+) -> io::Result<()> {
+    client_session.complete_io(tcp_stream)?;
+
+    let mut tls_stream = Stream::new(client_session, tcp_stream);
+
+    tls_stream.write_all(b"foo")?;
+    println!("client tls_stream write foo done");
+
+    let mut buf = [0; 5];
+    let n = tls_stream.read(&mut buf)?;
+    assert_eq!(n, 3);
+    assert_eq!(&buf, b"bar\0\0");
+    println!("client tls_stream read bar done");
+
+    let mut buf = [0; 5];
+    let err = tls_stream.read(&mut buf).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionAborted);
+    println!("client tls_stream read close_notify done");
+
+    sender
+        .send("client_done".to_owned())
+        .map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e))?;
+
+    Ok(())
+}
+
+#[allow(dead_code)]
+pub async fn run_sync_server_half_close(
+    server_session: &mut ServerSession,
+    tcp_stream: &mut TcpStream,
+    sender: Sender<String>,
+) -> io::Result<()> {
+    server_session.complete_io(tcp_stream)?;
+
+    let mut tls_stream = Stream::new(server_session, tcp_stream);
+
+    let mut buf = [0; 5];
+    let n = tls_stream.read(&mut buf)?;
+    assert_eq!(n, 3);
+    assert_eq!(&buf, b"foo\0\0");
+    println!("server tls_stream read foo done");
+
+    // rustls's Stream keeps reading the socket after close_notify, so drive the session by hand.
+    let mut buf = [0; 5];
+    let err = loop {
+        match tls_stream.sess.read(&mut buf) {
+            Ok(0) => {}
+            Ok(n) => panic!("unexpected {} bytes", n),
+            Err(err) => break err,
+        }
+        tls_stream.sess.read_tls(tls_stream.sock)?;
+        tls_stream
+            .sess
+            .process_new_packets()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    };
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionAborted);
+    println!("server tls_stream read close_notify done");
+
+    tls_stream.write_all(b"bar")?;
+    println!("server tls_stream write bar done");
+
+    tls_stream.sess.send_close_notify();
+    tls_stream.flush()?;
+    println!("server tls_stream send close_notify done");
+
+    sender
+        .send("server_done".to_owned())
+        .map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e))?;
+
+    Ok(())
+}
+
+pub fn get_mkcert_path() -> PathBuf {
     PathBuf::new().join("mkcert")
 }
+
+/// A `TlsAcceptor::builder()` with the mkcert cert `name`, e.g. `tls.lvh.me`.
+#[cfg(all(feature = "acceptor", feature = "connector"))]
+#[allow(dead_code)]
+pub fn acceptor_builder(name: &str) -> TlsAcceptorBuilder {
+    let mkcert_path = get_mkcert_path();
+
+    TlsAcceptor::builder()
+        .cert_chain_pem_file(mkcert_path.join(format!("{}.crt", name)))
+        .private_key_pem_file(mkcert_path.join(format!("{}-key.pem", name)))
+}
+
+/// A `TlsConnector::builder()` trusting the mkcert root.
+#[cfg(all(feature = "acceptor", feature = "connector"))]
+#[allow(dead_code)]
+pub fn connector_builder() -> TlsConnectorBuilder {
+    TlsConnector::builder().root_cert_pem_file(get_mkcert_path().join("rootCA.pem"))
+}
+
+#[cfg(all(feature = "acceptor", feature = "connector"))]
+pub type ServerStream = TlsStream<ServerSession, DuplexStream>;
+#[cfg(all(feature = "acceptor", feature = "connector"))]
+pub type ClientStream = TlsStream<ClientSession, DuplexStream>;
+
+/// Accepts with `acceptor` and connects to `server_name` with `connector` over a `duplex`.
+/// After both handshakes the server writes `foo` and the client reads it, so the client has
+/// seen what the server sends after its handshake too, e.g. TLS 1.3 tickets.
+#[cfg(all(feature = "acceptor", feature = "connector"))]
+#[allow(dead_code)]
+pub async fn connect_pair(
+    acceptor: &TlsAcceptor,
+    connector: &TlsConnector,
+    server_name: impl IntoServerName,
+) -> (io::Result<ServerStream>, io::Result<ClientStream>) {
+    connect_pair_with(|stream| acceptor.accept(stream), connector, server_name).await
+}
+
+/// The server's and the client's `ConnectionInfo` after `connect_pair`.
+#[cfg(all(feature = "acceptor", feature = "connector"))]
+#[allow(dead_code)]
+pub async fn connection_infos(
+    acceptor: &TlsAcceptor,
+    connector: &TlsConnector,
+    server_name: impl IntoServerName,
+) -> io::Result<(ConnectionInfo, ConnectionInfo)> {
+    let (server, client) = connect_pair(acceptor, connector, server_name).await;
+    // The client's error says why, the server only sees it go away.
+    let client_info = client?.connection_info();
+    Ok((server?.connection_info(), client_info))
+}
+
+/// Like `connect_pair`, with `accept` for the server's handshake.
+#[cfg(all(feature = "acceptor", feature = "connector"))]
+#[allow(dead_code)]
+pub async fn connect_pair_with<A, F>(
+    accept: A,
+    connector: &TlsConnector,
+    server_name: impl IntoServerName,
+) -> (io::Result<ServerStream>, io::Result<ClientStream>)
+where
+    A: FnOnce(DuplexStream) -> F,
+    F: std::future::Future<Output = io::Result<ServerStream>>,
+{
+    let (stream_c, stream_s) = duplex();
+
+    let server = async {
+        let mut tls_stream = accept(stream_s).await?;
+        tls_stream.write_all(b"foo").await?;
+        tls_stream.flush().await?;
+        Ok(tls_stream)
+    };
+    let client = async {
+        let mut tls_stream = connector.connect(server_name, stream_c).await?;
+        let mut buf = [0; 3];
+        tls_stream.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"foo");
+        Ok(tls_stream)
+    };
+
+    join(server, client).await
+}
+
+/// Like a socket's send buffer, writes are `Pending` once it's full.
+const PIPE_CAPACITY: usize = 64 * 1024;
+
+#[derive(Default)]
+struct Pipe {
+    buf: VecDeque<u8>,
+    /// The writing end closed or dropped, the reader sees EOF once `buf` is drained.
+    write_closed: bool,
+    /// The reading end dropped, writes fail.
+    read_closed: bool,
+    read_waker: Option<Waker>,
+    write_waker: Option<Waker>,
+}
+
+fn lock(pipe: &Mutex<Pipe>) -> std::sync::MutexGuard<'_, Pipe> {
+    pipe.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// One end of an in-memory connection, see `duplex`.
+pub struct DuplexStream {
+    read: Arc<Mutex<Pipe>>,
+    write: Arc<Mutex<Pipe>>,
+}
+
+/// Two connected in-memory streams, like `UnixStream::pair` on any platform.
+#[allow(dead_code)]
+pub fn duplex() -> (DuplexStream, DuplexStream) {
+    let a = Arc::new(Mutex::new(Pipe::default()));
+    let b = Arc::new(Mutex::new(Pipe::default()));
+
+    (
+        DuplexStream {
+            read: a.clone(),
+            write: b.clone(),
+        },
+        DuplexStream { read: b, write: a },
+    )
+}
+
+impl AsyncRead for DuplexStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut pipe = lock(&self.read);
+
+        if pipe.buf.is_empty() {
+            if pipe.write_closed || buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+            pipe.read_waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        let n = buf.len().min(pipe.buf.len());
+        for (dst, src) in buf.iter_mut().zip(pipe.buf.drain(..n)) {
+            *dst = src;
+        }
+        if let Some(waker) = pipe.write_waker.take() {
+            waker.wake();
+        }
+
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl AsyncWrite for DuplexStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let mut pipe = lock(&self.write);
+
+        if pipe.write_closed || pipe.read_closed {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+
+        let n = buf.len().min(PIPE_CAPACITY - pipe.buf.len());
+        if n == 0 && !buf.is_empty() {
+            pipe.write_waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        pipe.buf.extend(&buf[..n]);
+        if let Some(waker) = pipe.read_waker.take() {
+            waker.wake();
+        }
+
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    /// Shuts down the write direction, the peer reads EOF.
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<io::Result<()>> {
+        let mut pipe = lock(&self.write);
+
+        pipe.write_closed = true;
+        if let Some(waker) = pipe.read_waker.take() {
+            waker.wake();
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for DuplexStream {
+    fn drop(&mut self) {
+        let mut pipe = lock(&self.write);
+        pipe.write_closed = true;
+        if let Some(waker) = pipe.read_waker.take() {
+            waker.wake();
+        }
+        drop(pipe);
+
+        let mut pipe = lock(&self.read);
+        pipe.read_closed = true;
+        if let Some(waker) = pipe.write_waker.take() {
+            waker.wake();
+        }
+    }
+}
@@ -0,0 +1,143 @@
+#![cfg(all(feature = "acceptor", feature = "connector"))]
+
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use futures_executor::block_on;
+use futures_util::future::join;
+
+use async_tls_lite::{IntoServerName, ServerName, TlsAcceptor};
+
+mod helper;
+
+async fn connect(
+    acceptor: &TlsAcceptor,
+    server_name: impl IntoServerName,
+) -> io::Result<Option<String>> {
+    let connector = helper::connector_builder().build()?;
+    let (server_info, _) = helper::connection_infos(acceptor, &connector, server_name).await?;
+    Ok(server_info.sni_hostname)
+}
+
+#[test]
+fn ip_address_san() -> io::Result<()> {
+    block_on(async {
+        let acceptor = helper::acceptor_builder("ip.lvh.me+2").build()?;
+
+        assert_eq!(connect(&acceptor, "127.0.0.1").await?, None);
+        assert_eq!(connect(&acceptor, "::1").await?, None);
+        assert_eq!(connect(&acceptor, Ipv4Addr::LOCALHOST).await?, None);
+        assert_eq!(connect(&acceptor, Ipv6Addr::LOCALHOST).await?, None);
+        assert_eq!(
+            connect(
+                &acceptor,
+                ServerName::IpAddress(IpAddr::from([127, 0, 0, 1]))
+            )
+            .await?,
+            None
+        );
+
+        assert_eq!(
+            connect(&acceptor, "ip.lvh.me").await?,
+            Some("ip.lvh.me".to_owned())
+        );
+
+        let err = connect(&acceptor, "127.0.0.2").await.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn no_ip_address_san() -> io::Result<()> {
+    block_on(async {
+        let acceptor = helper::acceptor_builder("tls.lvh.me").build()?;
+
+        let err = connect(&acceptor, "127.0.0.1").await.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn connect_with_fallback_to_ip_address() -> io::Result<()> {
+    block_on(async {
+        let acceptor = helper::acceptor_builder("ip.lvh.me+2").build()?;
+        let connector = helper::connector_builder().build()?;
+
+        let (stream_c, stream_s) = helper::duplex();
+        let (server, client) = join(
+            acceptor.accept(stream_s),
+            connector.connect_with_fallback("127.0.0.1", stream_c),
+        )
+        .await;
+        client?;
+        assert_eq!(server?.connection_info().sni_hostname, None);
+
+        // The stream is handed back untouched.
+        let (stream_c, _stream_s) = helper::duplex();
+        let err = connector
+            .connect_with_fallback("ip-7f000001.invalid", stream_c)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.error.kind(), io::ErrorKind::InvalidInput);
+        assert!(err.read_bytes.is_empty());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn into_server_name() -> io::Result<()> {
+    assert_eq!(
+        "127.0.0.1".into_server_name()?,
+        ServerName::IpAddress(IpAddr::from([127, 0, 0, 1]))
+    );
+    assert_eq!(
+        "tls.lvh.me".to_owned().into_server_name()?,
+        ServerName::DnsName("tls.lvh.me".to_owned())
+    );
+    assert_eq!(
+        "invalid domain".into_server_name().err().unwrap().kind(),
+        io::ErrorKind::InvalidInput
+    );
+
+    // How IP addresses are passed to the verifier, not a DNS name to verify as one.
+    for name in &[
+        "ip-7f000001.invalid",
+        "ip-00000000000000000000000000000001.invalid",
+    ] {
+        assert_eq!(
+            name.into_server_name().err().unwrap().kind(),
+            io::ErrorKind::InvalidInput
+        );
+        assert_eq!(
+            ServerName::DnsName(name.to_string())
+                .into_server_name()
+                .err()
+                .unwrap()
+                .kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn placeholder_shaped_name_refused() -> io::Result<()> {
+    block_on(async {
+        let acceptor = helper::acceptor_builder("ip.lvh.me+2").build()?;
+
+        let err = connect(&acceptor, "ip-7f000001.invalid")
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        Ok(())
+    })
+}
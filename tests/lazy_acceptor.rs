@@ -0,0 +1,166 @@
+#![cfg(all(feature = "acceptor", feature = "connector"))]
+
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_io::Timer;
+use futures_executor::block_on;
+use futures_util::future::join;
+use futures_util::io::AsyncWriteExt;
+
+use async_tls_lite::prelude::{CipherSuite, NoClientAuth, ProtocolVersion, ServerConfig};
+use async_tls_lite::{ConnectionInfo, LazyAcceptor, TlsConnector};
+
+mod helper;
+
+fn server_config(name: &str) -> io::Result<Arc<ServerConfig>> {
+    let (certs, key) = helper::get_cert_and_key(name)?;
+    let mut server_config = ServerConfig::new(NoClientAuth::new());
+    server_config
+        .set_single_cert(certs, key)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    Ok(Arc::new(server_config))
+}
+
+/// Accepts with the config of the tenant named by SNI.
+async fn connect(
+    tenants: &[(&str, Arc<ServerConfig>)],
+    connector: &TlsConnector,
+    server_name: &str,
+) -> io::Result<ConnectionInfo> {
+    let accept = |stream_s| async {
+        let start = LazyAcceptor::new().accept(stream_s).await?;
+
+        let client_hello = start.client_hello();
+        assert_eq!(client_hello.alpn_protocols, vec![b"h2".to_vec()]);
+        assert!(client_hello
+            .cipher_suites
+            .contains(&CipherSuite::TLS13_AES_128_GCM_SHA256));
+        assert!(!client_hello.signature_schemes.is_empty());
+
+        let config = tenants
+            .iter()
+            .find(|(name, _)| Some(*name) == client_hello.server_name.as_deref())
+            .map(|(_, config)| config.clone())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "unknown tenant"))?;
+
+        start.into_accept(config).await
+    };
+
+    let (server, client) = helper::connect_pair_with(accept, connector, server_name).await;
+    let info = server?.connection_info();
+    client?;
+
+    Ok(info)
+}
+
+fn connector(versions: &[ProtocolVersion]) -> io::Result<TlsConnector> {
+    let mut client_config = helper::get_client_config()?;
+    client_config.alpn_protocols = vec![b"h2".to_vec()];
+    client_config.versions = versions.to_vec();
+    Ok(TlsConnector::from(Arc::new(client_config)))
+}
+
+#[test]
+fn config_per_tenant() -> io::Result<()> {
+    block_on(async {
+        let tenants = [
+            ("tls.lvh.me", server_config("tls.lvh.me")?),
+            ("a.lvh.me", server_config("a.lvh.me")?),
+        ];
+        let connector = connector(&[ProtocolVersion::TLSv1_3, ProtocolVersion::TLSv1_2])?;
+
+        for (name, _) in &tenants {
+            let info = connect(&tenants, &connector, name).await?;
+            assert_eq!(info.sni_hostname.as_deref(), Some(*name));
+            assert_eq!(info.protocol_version, Some(ProtocolVersion::TLSv1_3));
+        }
+
+        let err = connect(&tenants, &connector, "b.lvh.me")
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn resumed() -> io::Result<()> {
+    for version in &[ProtocolVersion::TLSv1_3, ProtocolVersion::TLSv1_2] {
+        block_on(async {
+            let tenants = [("tls.lvh.me", server_config("tls.lvh.me")?)];
+            let connector = connector(&[*version])?;
+
+            let info = connect(&tenants, &connector, "tls.lvh.me").await?;
+            assert_eq!(info.protocol_version, Some(*version));
+            assert!(!info.resumed);
+
+            let info = connect(&tenants, &connector, "tls.lvh.me").await?;
+            assert!(info.resumed);
+
+            Ok::<_, io::Error>(())
+        })?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn not_tls() -> io::Result<()> {
+    block_on(async {
+        let (mut stream_c, stream_s) = helper::duplex();
+
+        let (ret, _) = join(
+            LazyAcceptor::new().accept(stream_s),
+            stream_c.write_all(b"GET / HTTP/1.1\r\n\r\n"),
+        )
+        .await;
+        let err = ret.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn into_inner() -> io::Result<()> {
+    block_on(async {
+        let (stream_c, stream_s) = helper::duplex();
+        let connector = connector(&[ProtocolVersion::TLSv1_3])?;
+
+        // Dropping the transport fails the client.
+        let server = async {
+            let start = LazyAcceptor::new().accept(stream_s).await?;
+            assert_eq!(
+                start.client_hello().server_name.as_deref(),
+                Some("tls.lvh.me")
+            );
+            Ok::<_, io::Error>(start.into_inner().1)
+        };
+        let (buffered, client) = join(server, connector.connect("tls.lvh.me", stream_c)).await;
+        assert_eq!(buffered?[0], 0x16);
+        assert!(client.is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn timed_out() -> io::Result<()> {
+    block_on(async {
+        let (_stream_c, stream_s) = helper::duplex();
+
+        let err = LazyAcceptor::new()
+            .with_handshake_timeout(Duration::from_millis(50), Timer::new)
+            .accept(stream_s)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+        Ok(())
+    })
+}
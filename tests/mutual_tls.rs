@@ -0,0 +1,100 @@
+#![cfg(all(feature = "acceptor", feature = "connector"))]
+
+use std::io;
+
+use futures_executor::block_on;
+
+use async_tls_lite::{TlsAcceptor, TlsConnector};
+use rustls::Certificate;
+
+mod helper;
+
+fn get_acceptor(required: bool) -> io::Result<TlsAcceptor> {
+    let root_ca = helper::get_mkcert_path().join("rootCA.pem");
+
+    let builder = helper::acceptor_builder("tls.lvh.me");
+    if required {
+        builder.client_auth_required(root_ca)
+    } else {
+        builder.client_auth_optional(root_ca)
+    }
+    .build()
+}
+
+fn get_connector_with_client_cert() -> io::Result<TlsConnector> {
+    let mkcert_path = helper::get_mkcert_path();
+
+    helper::connector_builder()
+        .client_cert_pem_file(mkcert_path.join("client.lvh.me-client.crt"))
+        .client_private_key_pem_file(mkcert_path.join("client.lvh.me-client-key.pem"))
+        .build()
+}
+
+async fn connect(
+    acceptor: &TlsAcceptor,
+    connector: &TlsConnector,
+) -> io::Result<Option<Vec<Certificate>>> {
+    let (server, client) = helper::connect_pair(acceptor, connector, "tls.lvh.me").await;
+    let client_certs = server?.client_certificates();
+    client?;
+
+    Ok(client_certs)
+}
+
+#[test]
+fn client_auth_required() -> io::Result<()> {
+    block_on(async {
+        let acceptor = get_acceptor(true)?;
+
+        let client_certs = connect(&acceptor, &get_connector_with_client_cert()?).await?;
+        assert_eq!(
+            client_certs,
+            Some(helper::get_certs("client.lvh.me-client")?)
+        );
+
+        let err = connect(&acceptor, &helper::connector_builder().build()?)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn client_auth_optional() -> io::Result<()> {
+    block_on(async {
+        let acceptor = get_acceptor(false)?;
+
+        let client_certs = connect(&acceptor, &get_connector_with_client_cert()?).await?;
+        assert_eq!(
+            client_certs,
+            Some(helper::get_certs("client.lvh.me-client")?)
+        );
+
+        let client_certs = connect(&acceptor, &helper::connector_builder().build()?).await?;
+        assert_eq!(client_certs, None);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn no_client_auth() -> io::Result<()> {
+    block_on(async {
+        let acceptor = helper::acceptor_builder("tls.lvh.me").build()?;
+
+        let client_certs = connect(&acceptor, &get_connector_with_client_cert()?).await?;
+        assert_eq!(client_certs, None);
+
+        let err = helper::connector_builder()
+            .client_cert_pem_file(helper::get_mkcert_path().join("client.lvh.me-client.crt"))
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        Ok(())
+    })
+}
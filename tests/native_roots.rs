@@ -0,0 +1,68 @@
+#![cfg(all(feature = "native-roots", feature = "acceptor"))]
+
+use std::env;
+use std::fs;
+use std::io;
+use std::sync::Arc;
+
+use futures_executor::block_on;
+
+use async_tls_lite::{TlsAcceptor, TlsConnector};
+
+mod helper;
+
+#[test]
+fn ssl_cert_file() -> io::Result<()> {
+    env::set_var(
+        "SSL_CERT_FILE",
+        helper::get_mkcert_path().join("rootCA.pem"),
+    );
+    env::remove_var("SSL_CERT_DIR");
+
+    block_on(async {
+        let acceptor = TlsAcceptor::from(Arc::new(helper::get_server_config()?));
+
+        for connector in [
+            TlsConnector::builder().webpki_roots(false).build()?,
+            TlsConnector::default(),
+        ] {
+            let (server, client) = helper::connect_pair(&acceptor, &connector, "tls.lvh.me").await;
+            server?;
+            client?;
+        }
+
+        // A bad file is skipped, the good one next to it still loads.
+        let dir = env::temp_dir().join(format!(
+            "async-tls-lite-native-roots-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("bad.pem"), "not a cert")?;
+        env::set_var("SSL_CERT_FILE", dir.join("bad.pem"));
+        env::set_var("SSL_CERT_DIR", &dir);
+
+        let err = TlsConnector::builder()
+            .webpki_roots(false)
+            .native_roots(true)
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        // Used by default, a missing trust store doesn't stop the connector.
+        TlsConnector::builder().build()?;
+
+        fs::copy(
+            helper::get_mkcert_path().join("rootCA.pem"),
+            dir.join("rootCA.pem"),
+        )?;
+        let connector = TlsConnector::builder().webpki_roots(false).build()?;
+        fs::remove_dir_all(&dir)?;
+
+        let (server, client) = helper::connect_pair(&acceptor, &connector, "tls.lvh.me").await;
+        server?;
+        client?;
+
+        Ok(())
+    })
+}
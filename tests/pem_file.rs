@@ -0,0 +1,80 @@
+#![cfg(all(feature = "acceptor", feature = "connector"))]
+
+use std::io;
+use std::sync::Arc;
+
+use futures_executor::block_on;
+
+use async_tls_lite::{TlsAcceptor, TlsConnector};
+
+mod helper;
+
+#[test]
+fn private_key_formats() -> io::Result<()> {
+    block_on(async {
+        let mkcert_path = helper::get_mkcert_path();
+
+        for (domain, key) in &[
+            ("tls.lvh.me", "tls.lvh.me-key.pem"),
+            ("a.lvh.me", "a.lvh.me-key.pkcs1.pem"),
+            ("ec.lvh.me", "ec.lvh.me-key.pem"),
+        ] {
+            let acceptor = TlsAcceptor::builder()
+                .cert_chain_pem_file(mkcert_path.join(format!("{}.crt", domain)))
+                .private_key_pem_file(mkcert_path.join(key))
+                .build()?;
+            let connector = TlsConnector::from(Arc::new(helper::get_client_config()?));
+
+            let (server, client) = helper::connect_pair(&acceptor, &connector, *domain).await;
+            server?;
+
+            let peer_certs = client?.connection_info().peer_certificates.unwrap();
+            assert_eq!(peer_certs, helper::get_certs(domain)?);
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn descriptive_errors() -> io::Result<()> {
+    let mkcert_path = helper::get_mkcert_path();
+
+    let err = TlsAcceptor::builder()
+        .cert_chain_pem_file(mkcert_path.join("missing.crt"))
+        .private_key_pem_file(mkcert_path.join("tls.lvh.me-key.pem"))
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert!(err.to_string().contains("missing.crt"));
+
+    let err = TlsAcceptor::builder()
+        .cert_chain_pem_file(mkcert_path.join("tls.lvh.me-key.pem"))
+        .private_key_pem_file(mkcert_path.join("tls.lvh.me-key.pem"))
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(err
+        .to_string()
+        .contains("tls.lvh.me-key.pem: no CERTIFICATE found"));
+
+    let err = TlsAcceptor::builder()
+        .cert_chain_pem_file(mkcert_path.join("tls.lvh.me.crt"))
+        .private_key_pem_file(mkcert_path.join("tls.lvh.me.crt"))
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(err.to_string().contains("tls.lvh.me.crt: no PRIVATE KEY"));
+
+    let err = TlsAcceptor::builder()
+        .cert_chain_pem_file(mkcert_path.join("tls.lvh.me.crt"))
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    Ok(())
+}
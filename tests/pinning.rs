@@ -0,0 +1,175 @@
+#![cfg(all(feature = "acceptor", feature = "connector"))]
+
+use std::io;
+use std::sync::Arc;
+
+use futures_executor::block_on;
+
+use async_tls_lite::prelude::{Certificate, NoClientAuth, PrivateKey, ServerConfig};
+use async_tls_lite::{Error, TlsAcceptor, TlsConnector};
+
+mod helper;
+
+// openssl x509 -in tls.lvh.me.pem -pubkey -noout | openssl pkey -pubin -outform der \
+//   | openssl dgst -sha256 -binary | base64
+const TLS_LVH_ME_PIN: &str = "7AAkQdWOimX8lqaDn8mBYuKznba99fUSSD9AP1V38FY=";
+const ROOT_CA_PIN: &str = "ZQZhe1HWYK0xQgtBCi6FV2jgvwc/xuBJPY8HIlnPyzk=";
+const A_LVH_ME_PIN: &str = "A0TV1pYF8vF4Y8+mIOxBuMnQMNdcLWUHTk0VLsgaPaU=";
+const CHAIN_INTERMEDIATE_CA_PIN: &str = "tBwPrX2ATxR1vJrow6bpqTsD4sgBMUMmLAr4I3UhB5Y=";
+
+async fn connect(connector: &TlsConnector) -> io::Result<()> {
+    let (certs, key) = helper::get_cert_and_key("tls.lvh.me")?;
+    connect_with_chain(connector, certs, key).await
+}
+
+/// Connects to a server sending `certs` as its chain.
+async fn connect_with_chain(
+    connector: &TlsConnector,
+    certs: Vec<Certificate>,
+    key: PrivateKey,
+) -> io::Result<()> {
+    let mut config = ServerConfig::new(NoClientAuth::new());
+    config
+        .set_single_cert(certs, key)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+
+    helper::connect_pair(&acceptor, connector, "tls.lvh.me")
+        .await
+        .1?;
+    Ok(())
+}
+
+#[test]
+fn pin_match() -> io::Result<()> {
+    block_on(async {
+        for pins in &[
+            vec![TLS_LVH_ME_PIN],
+            vec![ROOT_CA_PIN],
+            vec![A_LVH_ME_PIN, TLS_LVH_ME_PIN],
+        ] {
+            let mut builder = helper::connector_builder();
+            for pin in pins {
+                builder = builder.pin_spki_sha256(*pin);
+            }
+
+            connect(&builder.build()?).await?;
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn pin_mismatch() -> io::Result<()> {
+    block_on(async {
+        let connector = helper::connector_builder()
+            .pin_spki_sha256(A_LVH_ME_PIN)
+            .build()?;
+
+        let err = connect(&connector).await.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(matches!(
+            err.get_ref().unwrap().downcast_ref::<Error>(),
+            Some(Error::PinMismatch)
+        ));
+
+        // The chain is verified before the pins.
+        let connector = TlsConnector::builder()
+            .pin_spki_sha256(TLS_LVH_ME_PIN)
+            .build()?;
+
+        let err = connect(&connector).await.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(matches!(
+            err.get_ref().unwrap().downcast_ref::<Error>(),
+            Some(Error::CertificateInvalid { .. })
+        ));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn root_pin_with_leaf_only_chain() -> io::Result<()> {
+    block_on(async {
+        let (mut certs, key) = helper::get_cert_and_key("tls.lvh.me")?;
+        certs.truncate(1);
+
+        let connector = helper::connector_builder()
+            .pin_spki_sha256(ROOT_CA_PIN)
+            .build()?;
+        connect_with_chain(&connector, certs, key).await
+    })
+}
+
+#[test]
+fn pinned_cert_appended_to_chain() -> io::Result<()> {
+    block_on(async {
+        // A valid chain, plus a cert that isn't on its path but is pinned.
+        let (mut certs, key) = helper::get_cert_and_key("tls.lvh.me")?;
+        certs.extend(helper::get_certs("a.lvh.me")?.into_iter().take(1));
+
+        let connector = helper::connector_builder()
+            .pin_spki_sha256(A_LVH_ME_PIN)
+            .build()?;
+        let err = connect_with_chain(&connector, certs, key)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.get_ref().unwrap().downcast_ref::<Error>(),
+            Some(Error::PinMismatch)
+        ));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn intermediate_pin() -> io::Result<()> {
+    block_on(async {
+        // chain.lvh.me is issued by an intermediate under its own root.
+        let acceptor = helper::acceptor_builder("chain.lvh.me").build()?;
+        let connector = TlsConnector::builder()
+            .root_cert_pem_file(helper::get_mkcert_path().join("chainRootCA.pem"))
+            .pin_spki_sha256(CHAIN_INTERMEDIATE_CA_PIN)
+            .build()?;
+
+        helper::connect_pair(&acceptor, &connector, "chain.lvh.me")
+            .await
+            .1?;
+
+        // Appended to a chain it didn't issue, the same intermediate doesn't count.
+        let (mut certs, key) = helper::get_cert_and_key("tls.lvh.me")?;
+        certs.extend(helper::get_certs("chain.lvh.me")?.into_iter().skip(1));
+
+        let connector = helper::connector_builder()
+            .pin_spki_sha256(CHAIN_INTERMEDIATE_CA_PIN)
+            .build()?;
+        let err = connect_with_chain(&connector, certs, key)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.get_ref().unwrap().downcast_ref::<Error>(),
+            Some(Error::PinMismatch)
+        ));
+
+        Ok(())
+    })
+}
+
+#[test]
+fn invalid_pin() -> io::Result<()> {
+    for pin in &["not base64!", "Zm9v"] {
+        let err = TlsConnector::builder()
+            .pin_spki_sha256(*pin)
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,183 @@
+#![cfg(all(feature = "proxy-protocol", feature = "connector"))]
+
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_io::Timer;
+use futures_executor::block_on;
+use futures_util::future::try_join;
+use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+
+use async_tls_lite::{read_proxy_header, ProxyHeader, TlsAcceptor, TlsConnector};
+
+mod helper;
+
+fn v2(version_command: u8, family: u8, body: &[u8]) -> Vec<u8> {
+    let mut header = b"\r\n\r\n\0\r\nQUIT\n".to_vec();
+    header.extend_from_slice(&[version_command, family]);
+    header.extend_from_slice(&(body.len() as u16).to_be_bytes());
+    header.extend_from_slice(body);
+    header
+}
+
+/// Parses `bytes` and checks what follows the header is left unread.
+fn parse(bytes: &[u8]) -> io::Result<ProxyHeader> {
+    block_on(async {
+        let mut bytes = bytes.to_vec();
+        bytes.extend_from_slice(b"\x16\x03");
+
+        let mut stream = &bytes[..];
+        let header = read_proxy_header(&mut stream).await?;
+        assert_eq!(stream, b"\x16\x03");
+
+        Ok(header)
+    })
+}
+
+fn addr(s: &str) -> Option<SocketAddr> {
+    Some(s.parse().unwrap())
+}
+
+#[test]
+fn accept_with_proxy_header() -> io::Result<()> {
+    let mut body = vec![127, 0, 0, 2, 127, 0, 0, 1, 0xc3, 0x50, 0x01, 0xbb];
+    body.extend_from_slice(b"\x02\x00\x0atls.lvh.me");
+
+    for header in &[
+        b"PROXY TCP4 127.0.0.2 127.0.0.1 50000 443\r\n".to_vec(),
+        v2(0x21, 0x11, &body),
+    ] {
+        block_on(async {
+            let acceptor = TlsAcceptor::from(Arc::new(helper::get_server_config()?));
+            let connector = TlsConnector::from(Arc::new(helper::get_client_config()?));
+            let (mut stream_c, stream_s) = helper::duplex();
+
+            let server = async {
+                let (proxy_header, mut tls_stream) =
+                    acceptor.accept_with_proxy_header(stream_s).await?;
+                tls_stream.write_all(b"foo").await?;
+                tls_stream.flush().await?;
+                Ok::<_, io::Error>(proxy_header)
+            };
+            let client = async {
+                stream_c.write_all(header).await?;
+                let mut tls_stream = connector.connect("tls.lvh.me", stream_c).await?;
+                let mut buf = [0; 3];
+                tls_stream.read_exact(&mut buf).await?;
+                assert_eq!(&buf, b"foo");
+                Ok(())
+            };
+
+            let (proxy_header, _) = try_join(server, client).await?;
+            assert_eq!(proxy_header.source, addr("127.0.0.2:50000"));
+            assert_eq!(proxy_header.destination, addr("127.0.0.1:443"));
+
+            Ok::<_, io::Error>(())
+        })?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn truncated_header_times_out() -> io::Result<()> {
+    for header in &[
+        &b"PROXY TCP4 127.0.0.2"[..],
+        &v2(0x21, 0x11, &[0; 12])[..20],
+    ] {
+        block_on(async {
+            let acceptor = TlsAcceptor::from(Arc::new(helper::get_server_config()?))
+                .with_handshake_timeout(Duration::from_millis(100), Timer::new);
+            let (mut stream_c, stream_s) = helper::duplex();
+            stream_c.write_all(header).await?;
+
+            let instant = Instant::now();
+            let err = acceptor
+                .accept_with_proxy_header(stream_s)
+                .await
+                .err()
+                .unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+            assert!(instant.elapsed() >= Duration::from_millis(100));
+
+            Ok::<_, io::Error>(())
+        })?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn v1() -> io::Result<()> {
+    let header = parse(b"PROXY TCP6 2001:db8::1 ::1 50000 443\r\n")?;
+    assert_eq!(header.version, 1);
+    assert_eq!(header.source, addr("[2001:db8::1]:50000"));
+    assert_eq!(header.destination, addr("[::1]:443"));
+
+    let header = parse(b"PROXY UNKNOWN\r\n")?;
+    assert_eq!(header.source, None);
+    assert_eq!(header.destination, None);
+
+    let header = parse(b"PROXY UNKNOWN ffff::1 ::1 1 2\r\n")?;
+    assert_eq!(header.source, None);
+
+    Ok(())
+}
+
+#[test]
+fn v2_addresses_and_tlvs() -> io::Result<()> {
+    let mut body = vec![0; 36];
+    body[15] = 2;
+    body[31] = 1;
+    body[32..].copy_from_slice(&[0xc3, 0x50, 0x01, 0xbb]);
+    body.extend_from_slice(b"\x01\x00\x02h2\x02\x00\x0atls.lvh.me\x04\x00\x00");
+
+    let header = parse(&v2(0x21, 0x21, &body))?;
+    assert_eq!(header.version, 2);
+    assert_eq!(header.source, addr("[::2]:50000"));
+    assert_eq!(header.destination, addr("[::1]:443"));
+    assert_eq!(header.tlvs.len(), 3);
+    assert_eq!(header.tlv(0x01), Some(&b"h2"[..]));
+    assert_eq!(header.tlv(0x02), Some(&b"tls.lvh.me"[..]));
+    assert_eq!(header.tlv(0x04), Some(&b""[..]));
+    assert_eq!(header.tlv(0x05), None);
+
+    // LOCAL, e.g. the load balancer's health checks.
+    let header = parse(&v2(0x20, 0x11, &[0; 12]))?;
+    assert_eq!(header.source, None);
+
+    let header = parse(&v2(0x21, 0x00, &[]))?;
+    assert_eq!(header.source, None);
+
+    let header = parse(&v2(0x21, 0x31, &[0; 216]))?;
+    assert_eq!(header.source, None);
+
+    Ok(())
+}
+
+#[test]
+fn malformed() {
+    let headers: Vec<Vec<u8>> = vec![
+        b"GET / HTTP/1.1\r\n\r\n".to_vec(),
+        b"\x16\x03\x01\x00\x04\x01\x00\x00\x00".to_vec(),
+        b"PROXY TCP4 127.0.0.1 127.0.0.1 1\r\n".to_vec(),
+        b"PROXY TCP4 ::1 ::1 1 2\r\n".to_vec(),
+        b"PROXY TCP4 127.0.0.1 127.0.0.1 1 65536\r\n".to_vec(),
+        b"PROXY UDP4 127.0.0.1 127.0.0.1 1 2\r\n".to_vec(),
+        [&b"PROXY UNKNOWN "[..], &[b'x'; 100], b"\r\n"].concat(),
+        b"\r\n\r\n\0\r\nQUIX\n\x21\x11\x00\x00".to_vec(),
+        v2(0x11, 0x11, &[0; 12]),
+        v2(0x22, 0x11, &[0; 12]),
+        v2(0x21, 0x41, &[]),
+        v2(0x21, 0x11, &[0; 11]),
+        v2(0x21, 0x11, &[&[0; 12][..], b"\x01\x00"].concat()),
+        v2(0x21, 0x11, &[&[0; 12][..], b"\x01\x00\x03h2"].concat()),
+    ];
+
+    for header in &headers {
+        let err = parse(header).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{:?}", header);
+    }
+}
@@ -0,0 +1,217 @@
+#![cfg(all(feature = "acceptor", feature = "connector"))]
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_io::Timer;
+use futures_executor::block_on;
+use futures_util::future::{select, Either};
+use futures_util::pin_mut;
+
+use async_tls_lite::{PemFileWatcher, TlsAcceptor, TlsConnector};
+use rustls::Certificate;
+
+mod helper;
+
+/// The chain the server presents for `domain`.
+async fn peer_certs(acceptor: &TlsAcceptor, domain: &str) -> io::Result<Vec<Certificate>> {
+    let connector = TlsConnector::from(Arc::new(helper::get_client_config()?));
+    let (server, client) = helper::connect_pair(acceptor, &connector, domain).await;
+    server?;
+
+    Ok(client?.connection_info().peer_certificates.unwrap())
+}
+
+fn copy_cert_and_key(name: &str, dir: &Path) -> io::Result<(PathBuf, PathBuf)> {
+    let mkcert_path = helper::get_mkcert_path();
+    let cert_path = dir.join("cert.pem");
+    let key_path = dir.join("key.pem");
+    fs::copy(mkcert_path.join(format!("{}.crt", name)), &cert_path)?;
+    fs::copy(mkcert_path.join(format!("{}-key.pem", name)), &key_path)?;
+
+    Ok((cert_path, key_path))
+}
+
+fn temp_dir(name: &str) -> io::Result<PathBuf> {
+    let dir = env::temp_dir().join(format!("async-tls-lite-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir)?;
+
+    Ok(dir)
+}
+
+#[test]
+fn set_config() -> io::Result<()> {
+    block_on(async {
+        let acceptor = TlsAcceptor::from(Arc::new(helper::get_server_config()?));
+        let cloned = acceptor.clone();
+        let connector = TlsConnector::from(Arc::new(helper::get_client_config()?));
+
+        let (certs, key) = helper::get_cert_and_key("a.lvh.me")?;
+        let mut server_config = helper::get_server_config()?;
+        server_config
+            .set_single_cert(certs.clone(), key)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+        // A handshake already in flight keeps the old config.
+        let accept = |stream_s| {
+            let in_flight = acceptor.accept(stream_s);
+            cloned.set_config(Arc::new(server_config));
+            in_flight
+        };
+        let (server, client) = helper::connect_pair_with(accept, &connector, "tls.lvh.me").await;
+        server?;
+        assert_eq!(
+            client?.connection_info().peer_certificates.unwrap(),
+            helper::get_cert_and_key("tls.lvh.me")?.0
+        );
+
+        assert_eq!(peer_certs(&acceptor, "a.lvh.me").await?, certs);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn reload_if_modified() -> io::Result<()> {
+    block_on(async {
+        let dir = temp_dir("reload_if_modified")?;
+
+        let acceptor = TlsAcceptor::from(Arc::new(helper::get_server_config()?));
+        let (cert_path, key_path) = copy_cert_and_key("tls.lvh.me", &dir)?;
+        let mut watcher = PemFileWatcher::new(&acceptor, &cert_path, &key_path)?;
+
+        // The files are what the acceptor already serves.
+        assert!(!watcher.reload_if_modified()?);
+
+        Timer::new(Duration::from_millis(10)).await;
+        copy_cert_and_key("a.lvh.me", &dir)?;
+        assert!(watcher.reload_if_modified()?);
+        assert!(!watcher.reload_if_modified()?);
+
+        assert_eq!(
+            peer_certs(&acceptor, "a.lvh.me").await?,
+            helper::get_cert_and_key("a.lvh.me")?.0
+        );
+
+        // A broken key keeps the old config.
+        fs::write(&key_path, "")?;
+        Timer::new(Duration::from_millis(10)).await;
+        assert!(watcher.reload_if_modified().is_err());
+
+        assert!(peer_certs(&acceptor, "a.lvh.me").await.is_ok());
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    })
+}
+
+#[test]
+fn mismatched_key() -> io::Result<()> {
+    block_on(async {
+        let dir = temp_dir("mismatched_key")?;
+        let mkcert_path = helper::get_mkcert_path();
+
+        let (cert_path, key_path) = copy_cert_and_key("ec.lvh.me", &dir)?;
+        let acceptor = TlsAcceptor::builder()
+            .cert_chain_pem_file(&cert_path)
+            .private_key_pem_file(&key_path)
+            .build()?;
+        let mut watcher = PemFileWatcher::new(&acceptor, &cert_path, &key_path)?;
+
+        // The cert is rotated before the key.
+        Timer::new(Duration::from_millis(10)).await;
+        fs::copy(mkcert_path.join("a.lvh.me.crt"), &cert_path)?;
+        let err = watcher.reload_if_modified().err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(
+            peer_certs(&acceptor, "ec.lvh.me").await?,
+            helper::get_certs("ec.lvh.me")?
+        );
+
+        fs::copy(mkcert_path.join("a.lvh.me-key.pem"), &key_path)?;
+        assert!(watcher.reload_if_modified()?);
+        assert_eq!(
+            peer_certs(&acceptor, "a.lvh.me").await?,
+            helper::get_certs("a.lvh.me")?
+        );
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    })
+}
+
+#[test]
+fn watch() -> io::Result<()> {
+    block_on(async {
+        let dir = temp_dir("watch")?;
+
+        let acceptor = TlsAcceptor::from(Arc::new(helper::get_server_config()?));
+        let (cert_path, key_path) = copy_cert_and_key("tls.lvh.me", &dir)?;
+        let watcher = PemFileWatcher::new(&acceptor, &cert_path, &key_path)?;
+
+        let watch = watcher.watch(Duration::from_millis(10), Timer::new, |res| {
+            res.unwrap();
+        });
+        let test = async {
+            Timer::new(Duration::from_millis(50)).await;
+            copy_cert_and_key("a.lvh.me", &dir)?;
+            Timer::new(Duration::from_millis(100)).await;
+
+            peer_certs(&acceptor, "a.lvh.me").await
+        };
+        pin_mut!(watch);
+        pin_mut!(test);
+
+        let certs = match select(watch, test).await {
+            Either::Left(_) => unreachable!(),
+            Either::Right((certs, _)) => certs?,
+        };
+        assert_eq!(certs, helper::get_cert_and_key("a.lvh.me")?.0);
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    })
+}
+
+#[test]
+fn watch_sni() -> io::Result<()> {
+    block_on(async {
+        let dir = temp_dir("watch_sni")?;
+
+        let (certs, key) = helper::get_cert_and_key("a.lvh.me")?;
+        let acceptor = helper::acceptor_builder("tls.lvh.me")
+            .cert("a.lvh.me", certs.clone(), key)
+            .build()?;
+        let (cert_path, key_path) = copy_cert_and_key("tls.lvh.me", &dir)?;
+        let watcher = PemFileWatcher::new(&acceptor, &cert_path, &key_path)?;
+
+        // Until the files change the watcher leaves the resolver alone.
+        let watch = watcher.watch(Duration::from_millis(10), Timer::new, |res| {
+            assert!(!res.unwrap());
+        });
+        let test = async {
+            Timer::new(Duration::from_millis(50)).await;
+
+            peer_certs(&acceptor, "a.lvh.me").await
+        };
+        pin_mut!(watch);
+        pin_mut!(test);
+
+        let served = match select(watch, test).await {
+            Either::Left(_) => unreachable!(),
+            Either::Right((served, _)) => served?,
+        };
+        assert_eq!(served, certs);
+
+        fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    })
+}
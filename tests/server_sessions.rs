@@ -0,0 +1,169 @@
+#![cfg(all(feature = "acceptor", feature = "connector"))]
+
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use futures_executor::block_on;
+
+use async_tls_lite::prelude::{ProducesTickets, ProtocolVersion, StoresServerSessions};
+use async_tls_lite::{LruServerSessionStore, RotatingTicketer, TlsAcceptor, TlsConnector};
+
+mod helper;
+
+fn connector(version: ProtocolVersion) -> io::Result<TlsConnector> {
+    let mut client_config = helper::get_client_config()?;
+    client_config.versions = vec![version];
+    Ok(TlsConnector::from(Arc::new(client_config)))
+}
+
+/// Whether the server side resumed, the client must agree.
+async fn resumed(acceptor: &TlsAcceptor, connector: &TlsConnector) -> io::Result<bool> {
+    let (server_info, client_info) =
+        helper::connection_infos(acceptor, connector, "tls.lvh.me").await?;
+    assert_eq!(server_info.resumed, client_info.resumed);
+    Ok(server_info.resumed)
+}
+
+const VERSIONS: &[ProtocolVersion] = &[ProtocolVersion::TLSv1_3, ProtocolVersion::TLSv1_2];
+
+#[test]
+fn tickets_across_clones() -> io::Result<()> {
+    for version in VERSIONS {
+        block_on(async {
+            let acceptor = helper::acceptor_builder("tls.lvh.me")
+                .session_tickets(Duration::from_secs(3600))
+                .session_cache_size(0)
+                .build()?;
+            let connector = connector(*version)?;
+
+            assert!(!resumed(&acceptor, &connector).await?);
+            assert!(resumed(&acceptor.clone(), &connector).await?);
+
+            Ok::<_, io::Error>(())
+        })?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn session_cache_across_clones() -> io::Result<()> {
+    for version in VERSIONS {
+        block_on(async {
+            let acceptor = helper::acceptor_builder("tls.lvh.me")
+                .session_cache_size(8)
+                .build()?;
+            let connector = connector(*version)?;
+
+            assert!(!resumed(&acceptor, &connector).await?);
+            assert!(resumed(&acceptor.clone(), &connector).await?);
+
+            let acceptor = helper::acceptor_builder("tls.lvh.me")
+                .session_cache_size(0)
+                .build()?;
+            let connector = self::connector(*version)?;
+
+            assert!(!resumed(&acceptor, &connector).await?);
+            assert!(!resumed(&acceptor, &connector).await?);
+
+            Ok::<_, io::Error>(())
+        })?;
+    }
+
+    Ok(())
+}
+
+struct CountingStore {
+    inner: LruServerSessionStore,
+    puts: AtomicUsize,
+}
+
+impl StoresServerSessions for CountingStore {
+    fn put(&self, key: Vec<u8>, value: Vec<u8>) -> bool {
+        self.puts.fetch_add(1, Ordering::SeqCst);
+        self.inner.put(key, value)
+    }
+
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.inner.get(key)
+    }
+
+    fn take(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.inner.take(key)
+    }
+}
+
+#[test]
+fn custom_session_store() -> io::Result<()> {
+    block_on(async {
+        let store = Arc::new(CountingStore {
+            inner: LruServerSessionStore::new(8),
+            puts: AtomicUsize::new(0),
+        });
+        let connector = connector(ProtocolVersion::TLSv1_2)?;
+
+        let acceptor = helper::acceptor_builder("tls.lvh.me")
+            .session_store(store.clone())
+            .build()?;
+        assert!(!resumed(&acceptor, &connector).await?);
+        assert_eq!(store.puts.load(Ordering::SeqCst), 1);
+
+        // Another acceptor backed by the same storage.
+        let acceptor = helper::acceptor_builder("tls.lvh.me")
+            .session_store(store.clone())
+            .build()?;
+        assert!(resumed(&acceptor, &connector).await?);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn ticket_keys_rotate() -> io::Result<()> {
+    let ticketer = RotatingTicketer::new(Duration::from_millis(100))?;
+    assert!(ticketer.enabled());
+
+    let first = ticketer.encrypt(b"foo").unwrap();
+    assert_eq!(ticketer.decrypt(&first), Some(b"foo".to_vec()));
+    assert_eq!(ticketer.decrypt(&first[1..]), None);
+
+    thread::sleep(Duration::from_millis(120));
+    let second = ticketer.encrypt(b"bar").unwrap();
+    assert_eq!(ticketer.decrypt(&first), Some(b"foo".to_vec()));
+    assert_eq!(ticketer.decrypt(&second), Some(b"bar".to_vec()));
+
+    thread::sleep(Duration::from_millis(120));
+    assert_eq!(ticketer.decrypt(&first), None);
+    assert_eq!(ticketer.decrypt(&second), Some(b"bar".to_vec()));
+
+    Ok(())
+}
+
+#[test]
+fn long_key_lifetime() -> io::Result<()> {
+    let ticketer = RotatingTicketer::new(Duration::from_secs(u64::MAX))?;
+    assert_eq!(ticketer.get_lifetime(), u32::MAX);
+
+    let ticket = ticketer.encrypt(b"foo").unwrap();
+    assert_eq!(ticketer.decrypt(&ticket), Some(b"foo".to_vec()));
+
+    Ok(())
+}
+
+#[test]
+fn lru_server_session_store() {
+    let store = LruServerSessionStore::new(2);
+
+    assert!(store.put(b"a".to_vec(), b"1".to_vec()));
+    assert!(store.put(b"b".to_vec(), b"2".to_vec()));
+    assert_eq!(store.get(b"a"), Some(b"1".to_vec()));
+    assert!(store.put(b"c".to_vec(), b"3".to_vec()));
+    assert_eq!(store.get(b"b"), None);
+
+    assert_eq!(store.take(b"a"), Some(b"1".to_vec()));
+    assert_eq!(store.get(b"a"), None);
+    assert_eq!(store.len(), 1);
+}
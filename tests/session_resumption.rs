@@ -0,0 +1,90 @@
+#![cfg(all(feature = "acceptor", feature = "connector"))]
+
+use std::io;
+use std::sync::Arc;
+
+use futures_executor::block_on;
+
+use async_tls_lite::prelude::{ProtocolVersion, StoresClientSessions};
+use async_tls_lite::{LruClientSessionStore, TlsAcceptor};
+
+mod helper;
+
+#[test]
+fn resumed_across_clones() -> io::Result<()> {
+    block_on(async {
+        let acceptor = TlsAcceptor::from(Arc::new(helper::get_server_config()?));
+        let connector = helper::connector_builder().build()?;
+
+        let (_, info) = helper::connection_infos(&acceptor, &connector, "tls.lvh.me").await?;
+        assert_eq!(info.protocol_version, Some(ProtocolVersion::TLSv1_3));
+        assert!(!info.resumed);
+
+        let connector = connector.clone();
+        let (_, info) = helper::connection_infos(&acceptor, &connector, "tls.lvh.me").await?;
+        assert!(info.resumed);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn shared_store() -> io::Result<()> {
+    block_on(async {
+        let acceptor = TlsAcceptor::from(Arc::new(helper::get_server_config()?));
+        let store = Arc::new(LruClientSessionStore::new(8));
+
+        let connector = helper::connector_builder()
+            .session_store(store.clone())
+            .build()?;
+        let (_, info) = helper::connection_infos(&acceptor, &connector, "tls.lvh.me").await?;
+        assert!(!info.resumed);
+        assert!(!store.is_empty());
+
+        let connector = helper::connector_builder().session_store(store).build()?;
+        let (_, info) = helper::connection_infos(&acceptor, &connector, "tls.lvh.me").await?;
+        assert!(info.resumed);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn disabled() -> io::Result<()> {
+    block_on(async {
+        let acceptor = TlsAcceptor::from(Arc::new(helper::get_server_config()?));
+        let connector = helper::connector_builder().session_cache_size(0).build()?;
+
+        let (_, info) = helper::connection_infos(&acceptor, &connector, "tls.lvh.me").await?;
+        assert!(!info.resumed);
+        let (_, info) = helper::connection_infos(&acceptor, &connector, "tls.lvh.me").await?;
+        assert!(!info.resumed);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn least_recently_used_evicted() {
+    let store = LruClientSessionStore::new(2);
+    assert_eq!(store.capacity(), 2);
+
+    assert!(store.put(b"a".to_vec(), b"1".to_vec()));
+    assert!(store.put(b"b".to_vec(), b"2".to_vec()));
+    assert_eq!(store.get(b"a"), Some(b"1".to_vec()));
+
+    assert!(store.put(b"c".to_vec(), b"3".to_vec()));
+    assert_eq!(store.len(), 2);
+    assert_eq!(store.get(b"b"), None);
+    assert_eq!(store.get(b"a"), Some(b"1".to_vec()));
+
+    assert!(store.put(b"c".to_vec(), b"4".to_vec()));
+    assert!(store.put(b"d".to_vec(), b"5".to_vec()));
+    assert_eq!(store.get(b"a"), None);
+    assert_eq!(store.get(b"c"), Some(b"4".to_vec()));
+    assert_eq!(store.get(b"d"), Some(b"5".to_vec()));
+
+    let store = LruClientSessionStore::new(0);
+    assert!(!store.put(b"a".to_vec(), b"1".to_vec()));
+    assert!(store.is_empty());
+}
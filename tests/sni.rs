@@ -0,0 +1,84 @@
+#![cfg(all(feature = "acceptor", feature = "connector"))]
+
+use std::io;
+use std::sync::Arc;
+
+use futures_executor::block_on;
+
+use async_tls_lite::{TlsAcceptor, TlsConnector};
+use rustls::Certificate;
+
+mod helper;
+
+async fn connect(acceptor: &TlsAcceptor, domain: &str) -> io::Result<Vec<Certificate>> {
+    let connector = TlsConnector::from(Arc::new(helper::get_client_config()?));
+    let (server, client) = helper::connect_pair(acceptor, &connector, domain).await;
+    server?;
+
+    Ok(client?.connection_info().peer_certificates.unwrap())
+}
+
+fn get_acceptor(with_default: bool) -> io::Result<TlsAcceptor> {
+    let (a_certs, a_key) = helper::get_cert_and_key("a.lvh.me")?;
+    let (wild_certs, wild_key) = helper::get_cert_and_key("_wildcard.wild.lvh.me")?;
+    let (default_certs, default_key) = helper::get_cert_and_key("tls.lvh.me")?;
+
+    let mut builder = TlsAcceptor::builder()
+        .cert("a.lvh.me", a_certs, a_key)
+        .cert("*.wild.lvh.me", wild_certs, wild_key);
+    if with_default {
+        builder = builder.default_cert(default_certs, default_key);
+    }
+    builder.build()
+}
+
+#[test]
+fn select_cert_by_sni() -> io::Result<()> {
+    block_on(async {
+        let acceptor = get_acceptor(true)?;
+
+        for (domain, name) in &[
+            ("a.lvh.me", "a.lvh.me"),
+            ("A.LVH.ME", "a.lvh.me"),
+            ("foo.wild.lvh.me", "_wildcard.wild.lvh.me"),
+            ("tls.lvh.me", "tls.lvh.me"),
+        ] {
+            let certs = connect(&acceptor, domain).await?;
+            assert_eq!(certs, helper::get_cert_and_key(name)?.0, "{}", domain);
+        }
+
+        // A wildcard only covers one label, so this gets the default cert.
+        assert!(connect(&acceptor, "foo.bar.wild.lvh.me").await.is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn no_default_cert() -> io::Result<()> {
+    block_on(async {
+        let acceptor = get_acceptor(false)?;
+
+        assert!(connect(&acceptor, "a.lvh.me").await.is_ok());
+        assert!(connect(&acceptor, "tls.lvh.me").await.is_err());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn reject_mismatched_cert() -> io::Result<()> {
+    let (certs, key) = helper::get_cert_and_key("a.lvh.me")?;
+
+    let err = TlsAcceptor::builder()
+        .cert("b.lvh.me", certs, key)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    let err = TlsAcceptor::builder().build().err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    Ok(())
+}
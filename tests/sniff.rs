@@ -0,0 +1,151 @@
+#![cfg(all(feature = "acceptor", feature = "connector"))]
+
+use std::io;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_io::Timer;
+use futures_executor::block_on;
+use futures_util::future::try_join;
+use futures_util::io::{AsyncReadExt, AsyncWriteExt};
+
+use async_tls_lite::{MaybeTls, TlsAcceptor, TlsConnector};
+
+mod helper;
+
+/// Echoes one line back, over TLS or not, and reports which.
+async fn serve(acceptor: &TlsAcceptor, stream: helper::DuplexStream) -> io::Result<&'static str> {
+    async fn echo<S: AsyncReadExt + AsyncWriteExt + Unpin>(mut stream: S) -> io::Result<()> {
+        let mut buf = vec![];
+        let mut byte = [0; 1];
+        while stream.read(&mut byte).await? == 1 {
+            buf.push(byte[0]);
+            if byte[0] == b'\n' {
+                break;
+            }
+        }
+        stream.write_all(&buf).await?;
+        stream.close().await
+    }
+
+    match acceptor.accept_or_plaintext(stream).await? {
+        MaybeTls::Tls(tls_stream) => echo(tls_stream).await.map(|_| "tls"),
+        MaybeTls::Plaintext(stream) => echo(stream).await.map(|_| "plaintext"),
+    }
+}
+
+#[test]
+fn tls_client() -> io::Result<()> {
+    block_on(async {
+        let acceptor = TlsAcceptor::from(Arc::new(helper::get_server_config()?));
+        let connector = TlsConnector::from(Arc::new(helper::get_client_config()?));
+        let (stream_c, stream_s) = helper::duplex();
+
+        let client = async {
+            let mut tls_stream = connector.connect("tls.lvh.me", stream_c).await?;
+            tls_stream.write_all(b"foo\n").await?;
+            let mut buf = vec![];
+            tls_stream.read_to_end(&mut buf).await?;
+            Ok(buf)
+        };
+
+        let (kind, buf) = try_join(serve(&acceptor, stream_s), client).await?;
+        assert_eq!(kind, "tls");
+        assert_eq!(buf, b"foo\n");
+
+        Ok(())
+    })
+}
+
+#[test]
+fn plaintext_client() -> io::Result<()> {
+    block_on(async {
+        let acceptor = TlsAcceptor::from(Arc::new(helper::get_server_config()?));
+
+        for request in &[&b"foo\n"[..], b"f"] {
+            let (mut stream_c, stream_s) = helper::duplex();
+
+            let client = async {
+                stream_c.write_all(request).await?;
+                stream_c.close().await?;
+                let mut buf = vec![];
+                stream_c.read_to_end(&mut buf).await?;
+                Ok(buf)
+            };
+
+            let (kind, buf) = try_join(serve(&acceptor, stream_s), client).await?;
+            assert_eq!(kind, "plaintext");
+            assert_eq!(&buf, request);
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn tls_record_with_garbage() -> io::Result<()> {
+    block_on(async {
+        let acceptor = TlsAcceptor::from(Arc::new(helper::get_server_config()?));
+        let (mut stream_c, stream_s) = helper::duplex();
+
+        let client = async {
+            // An empty ClientHello.
+            stream_c
+                .write_all(b"\x16\x03\x01\x00\x04\x01\x00\x00\x00")
+                .await?;
+            stream_c.close().await?;
+            Ok(())
+        };
+
+        let err = try_join(serve(&acceptor, stream_s), client)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn decided_on_first_byte() -> io::Result<()> {
+    block_on(async {
+        let acceptor = TlsAcceptor::from(Arc::new(helper::get_server_config()?))
+            .with_handshake_timeout(Duration::from_millis(100), Timer::new);
+
+        // Plaintext without waiting for more.
+        let (mut stream_c, stream_s) = helper::duplex();
+        stream_c.write_all(b"f").await?;
+        match acceptor.accept_or_plaintext(stream_s).await? {
+            MaybeTls::Plaintext(stream) => assert_eq!(stream.prefix(), b"f"),
+            MaybeTls::Tls(_) => panic!("took plaintext for tls"),
+        }
+
+        // A handshake record type is TLS, whatever follows.
+        let (mut stream_c, stream_s) = helper::duplex();
+        stream_c.write_all(b"\x16\n").await?;
+        stream_c.close().await?;
+        let err = acceptor.accept_or_plaintext(stream_s).await.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn stalled_after_one_byte() -> io::Result<()> {
+    block_on(async {
+        let acceptor = TlsAcceptor::from(Arc::new(helper::get_server_config()?))
+            .with_handshake_timeout(Duration::from_millis(100), Timer::new);
+        let (mut stream_c, stream_s) = helper::duplex();
+        // Taken for TLS, the rest of the handshake is under the deadline.
+        stream_c.write_all(b"\x16").await?;
+
+        let instant = Instant::now();
+        let err = acceptor.accept_or_plaintext(stream_s).await.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(instant.elapsed() >= Duration::from_millis(100));
+
+        Ok(())
+    })
+}
@@ -0,0 +1,159 @@
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use async_io::{Async, Timer};
+use futures_executor::{block_on, ThreadPool};
+use futures_util::future::try_join;
+use futures_util::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use futures_util::task::{waker, ArcWake, SpawnExt};
+
+use async_tls_lite::{client_handshake, server_handshake, Eof};
+
+mod helper;
+
+#[test]
+fn read_and_write_from_different_tasks() -> io::Result<()> {
+    block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+
+        let client_session = helper::get_client_session()?;
+        let server_session = helper::get_server_session()?;
+
+        let tcp_stream_c = Async::<TcpStream>::new(TcpStream::connect(addr)?)?;
+        let tcp_stream_s = Async::<TcpStream>::new(
+            listener
+                .incoming()
+                .next()
+                .expect("Get next incoming failed")?,
+        )?;
+
+        let executor = ThreadPool::builder().pool_size(2).create()?;
+
+        executor
+            .spawn(async move {
+                let mut tls_stream = server_handshake(server_session, tcp_stream_s)
+                    .await
+                    .unwrap();
+
+                let mut buf = [0; 64];
+                loop {
+                    let n = tls_stream.read(&mut buf).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    tls_stream.write_all(&buf[..n]).await.unwrap();
+                }
+                assert_eq!(tls_stream.eof(), Some(Eof::CloseNotify));
+
+                tls_stream.close().await.unwrap();
+                println!("server close done");
+            })
+            .expect("failed to spawn");
+
+        let tls_stream = client_handshake(client_session, tcp_stream_c).await?;
+        let (mut read_half, mut write_half) = tls_stream.split();
+        assert!(read_half.is_pair_of(&write_half));
+
+        let (sender_r, receiver_r) = mpsc::channel();
+        let (sender_w, receiver_w) = mpsc::channel();
+
+        executor
+            .spawn(async move {
+                let mut received = vec![];
+                read_half.read_to_end(&mut received).await.unwrap();
+                println!("client read_half read_to_end done");
+
+                sender_r.send((read_half, received)).unwrap();
+            })
+            .expect("failed to spawn");
+
+        executor
+            .spawn(async move {
+                for i in 0..10 {
+                    Timer::new(Duration::from_millis(10)).await;
+                    write_half
+                        .write_all(format!("foo{}", i).as_bytes())
+                        .await
+                        .unwrap();
+                }
+                write_half.close().await.unwrap();
+                println!("client write_half close done");
+
+                sender_w.send(write_half).unwrap();
+            })
+            .expect("failed to spawn");
+
+        let (read_half, received) = receiver_r.recv().unwrap();
+        let write_half = receiver_w.recv().unwrap();
+
+        let expected: String = (0..10).map(|i| format!("foo{}", i)).collect();
+        assert_eq!(received, expected.as_bytes());
+
+        let tls_stream = read_half
+            .reunite(write_half)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        assert!(tls_stream.is_read_shutdown());
+        assert!(tls_stream.is_write_shutdown());
+        assert_eq!(tls_stream.eof(), Some(Eof::CloseNotify));
+
+        Ok(())
+    })
+}
+
+#[derive(Default)]
+struct WakeFlag(AtomicBool);
+
+impl ArcWake for WakeFlag {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn read_does_not_take_the_writers_wakeup() -> io::Result<()> {
+    block_on(async {
+        // Like most sockets, the pipe only keeps the waker of the last pending write.
+        let (stream_c, stream_s) = helper::duplex();
+
+        let (tls_stream_c, mut tls_stream_s) = try_join(
+            client_handshake(helper::get_client_session()?, stream_c),
+            server_handshake(helper::get_server_session()?, stream_s),
+        )
+        .await?;
+        let (mut read_half, mut write_half) = tls_stream_c.split();
+
+        // Fill the pipe until the write half waits for it to drain.
+        let write_flag = Arc::new(WakeFlag::default());
+        let write_waker = waker(write_flag.clone());
+        let mut cx = Context::from_waker(&write_waker);
+        let data = vec![0; 256 * 1024];
+        let mut written = 0;
+        while written < data.len() {
+            match Pin::new(&mut write_half).poll_write(&mut cx, &data[written..]) {
+                Poll::Ready(n) => written += n?,
+                Poll::Pending => break,
+            }
+        }
+        assert!(Pin::new(&mut write_half).poll_flush(&mut cx).is_pending());
+
+        // Reading with a write still pending must leave the writer's waker in place.
+        tls_stream_s.write_all(b"ping").await?;
+        let mut buf = [0; 4];
+        read_half.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"ping");
+        assert!(!write_flag.0.load(Ordering::SeqCst));
+
+        // Draining the pipe wakes the writer to send the rest.
+        let mut received = vec![0; 1024];
+        tls_stream_s.read_exact(&mut received).await?;
+        assert!(write_flag.0.load(Ordering::SeqCst));
+
+        Ok(())
+    })
+}
@@ -0,0 +1,339 @@
+#![cfg(all(feature = "acceptor", feature = "connector"))]
+
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_io::Timer;
+use futures_executor::block_on;
+use futures_util::future::{join, try_join};
+use futures_util::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+use async_tls_lite::starttls;
+use async_tls_lite::{TlsAcceptor, TlsConnector};
+
+mod helper;
+
+fn acceptor() -> io::Result<TlsAcceptor> {
+    Ok(TlsAcceptor::from(Arc::new(helper::get_server_config()?)))
+}
+
+fn connector() -> io::Result<TlsConnector> {
+    Ok(TlsConnector::from(Arc::new(helper::get_client_config()?)))
+}
+
+/// Upgrades with the `server` and `client` sides of a protocol, then exchanges a message.
+macro_rules! upgrade {
+    ($server:expr, $client:expr) => {
+        async {
+            let server = async {
+                let mut tls_stream = $server.await?;
+                tls_stream.write_all(b"foo").await?;
+                tls_stream.flush().await?;
+                Ok::<_, io::Error>(())
+            };
+            let client = async {
+                let mut tls_stream = $client.await?;
+                let mut buf = [0; 3];
+                tls_stream.read_exact(&mut buf).await?;
+                assert_eq!(&buf, b"foo");
+                Ok::<_, io::Error>(())
+            };
+            try_join(server, client).await.map(|_| ())
+        }
+    };
+}
+
+#[test]
+fn upgraded() -> io::Result<()> {
+    block_on(async {
+        let acceptor = acceptor()?;
+        let connector = connector()?;
+
+        let (stream_c, stream_s) = helper::duplex();
+        upgrade!(
+            starttls::smtp_accept(&acceptor, stream_s, "tls.lvh.me"),
+            starttls::smtp_connect(&connector, "tls.lvh.me", stream_c, "client.lvh.me")
+        )
+        .await?;
+
+        let (stream_c, stream_s) = helper::duplex();
+        upgrade!(
+            starttls::imap_accept(&acceptor, stream_s),
+            starttls::imap_connect(&connector, "tls.lvh.me", stream_c)
+        )
+        .await?;
+
+        let (stream_c, stream_s) = helper::duplex();
+        upgrade!(
+            starttls::pop3_accept(&acceptor, stream_s),
+            starttls::pop3_connect(&connector, "tls.lvh.me", stream_c)
+        )
+        .await?;
+
+        let (stream_c, stream_s) = helper::duplex();
+        upgrade!(
+            starttls::postgres_accept(&acceptor, stream_s),
+            starttls::postgres_connect(&connector, "tls.lvh.me", stream_c)
+        )
+        .await?;
+
+        Ok(())
+    })
+}
+
+/// Writes `script` lines and checks the replies, like a mock peer.
+async fn converse(
+    stream: &mut BufReader<helper::DuplexStream>,
+    script: &[(&str, &str)],
+) -> io::Result<()> {
+    for (request, reply) in script {
+        if !request.is_empty() {
+            stream.get_mut().write_all(request.as_bytes()).await?;
+        }
+        let mut line = String::new();
+        stream.read_line(&mut line).await?;
+        assert!(line.starts_with(reply), "{:?} got {:?}", request, line);
+    }
+    Ok(())
+}
+
+#[test]
+fn mock_clients() -> io::Result<()> {
+    block_on(async {
+        let acceptor = acceptor()?;
+        let connector = connector()?;
+
+        // Commands other than the upgrade are refused first.
+        let (stream_c, stream_s) = helper::duplex();
+        let client = async {
+            let mut stream = BufReader::new(stream_c);
+            converse(
+                &mut stream,
+                &[
+                    ("", "220 tls.lvh.me"),
+                    ("MAIL FROM:<a@lvh.me>\r\n", "530 "),
+                    ("HELO client.lvh.me\r\n", "250 tls.lvh.me"),
+                    ("STARTTLS\r\n", "220 "),
+                ],
+            )
+            .await?;
+            connector.connect("tls.lvh.me", stream.into_inner()).await
+        };
+        let (server, client) = join(
+            starttls::smtp_accept(&acceptor, stream_s, "tls.lvh.me"),
+            client,
+        )
+        .await;
+        server?;
+        client?;
+
+        let (stream_c, stream_s) = helper::duplex();
+        let client = async {
+            let mut stream = BufReader::new(stream_c);
+            converse(
+                &mut stream,
+                &[
+                    ("", "* OK"),
+                    ("t1 LOGIN a b\r\n", "t1 BAD"),
+                    ("t2 CAPABILITY\r\n", "* CAPABILITY IMAP4rev1 STARTTLS"),
+                    ("", "t2 OK"),
+                    ("t3 LOGOUT\r\n", "* BYE"),
+                ],
+            )
+            .await
+        };
+        let (server, client) = join(starttls::imap_accept(&acceptor, stream_s), client).await;
+        assert_eq!(server.err().unwrap().kind(), io::ErrorKind::InvalidData);
+        client?;
+
+        let (stream_c, stream_s) = helper::duplex();
+        let client = async {
+            let mut stream = BufReader::new(stream_c);
+            converse(
+                &mut stream,
+                &[
+                    ("", "+OK"),
+                    ("USER a\r\n", "-ERR"),
+                    ("CAPA\r\n", "+OK"),
+                    ("", "STLS"),
+                    ("", "."),
+                    ("QUIT\r\n", "+OK"),
+                ],
+            )
+            .await
+        };
+        let (server, client) = join(starttls::pop3_accept(&acceptor, stream_s), client).await;
+        assert!(server.is_err());
+        client?;
+
+        // A plaintext StartupMessage instead of an SSLRequest.
+        let (mut stream_c, stream_s) = helper::duplex();
+        let (server, client) = join(
+            starttls::postgres_accept(&acceptor, stream_s),
+            stream_c.write_all(&[0, 0, 0, 8, 0, 3, 0, 0]),
+        )
+        .await;
+        assert_eq!(server.err().unwrap().kind(), io::ErrorKind::InvalidData);
+        client?;
+
+        Ok(())
+    })
+}
+
+#[test]
+fn plaintext_after_upgrade_command_not_taken_for_tls() -> io::Result<()> {
+    block_on(async {
+        let acceptor = acceptor()?;
+
+        let (stream_c, stream_s) = helper::duplex();
+        let client = async {
+            let mut stream = BufReader::new(stream_c);
+            converse(
+                &mut stream,
+                &[("", "220 "), ("STARTTLS\r\nRSET\r\n", "220 ")],
+            )
+            .await?;
+            Ok::<_, io::Error>(stream)
+        };
+        let (server, client) = join(
+            starttls::smtp_accept(&acceptor, stream_s, "tls.lvh.me"),
+            client,
+        )
+        .await;
+        assert_eq!(server.err().unwrap().kind(), io::ErrorKind::InvalidData);
+        client?;
+
+        Ok(())
+    })
+}
+
+#[test]
+fn server_limits() -> io::Result<()> {
+    block_on(async {
+        let acceptor = acceptor()?;
+
+        // Too many commands without the upgrade.
+        let (stream_c, stream_s) = helper::duplex();
+        let client = async {
+            let mut stream = BufReader::new(stream_c);
+            let mut script = vec![("", "220 tls.lvh.me")];
+            script.extend(vec![("NOOP\r\n", "530 "); 16]);
+            script.push(("", "421 "));
+            converse(&mut stream, &script).await
+        };
+        let (server, client) = join(
+            starttls::smtp_accept(&acceptor, stream_s, "tls.lvh.me"),
+            client,
+        )
+        .await;
+        let err = server.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("too many commands"));
+        client?;
+
+        // A command line too long.
+        let (stream_c, stream_s) = helper::duplex();
+        let client = async {
+            let mut stream = BufReader::new(stream_c);
+            converse(&mut stream, &[("", "* OK")]).await?;
+            let line = format!("t1 LOGIN {}\r\n", "a".repeat(1024));
+            stream.get_mut().write_all(line.as_bytes()).await
+        };
+        let (server, client) = join(starttls::imap_accept(&acceptor, stream_s), client).await;
+        let err = server.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("line too long"));
+        client?;
+
+        // A client that never sends the upgrade command.
+        let acceptor = acceptor.with_handshake_timeout(Duration::from_millis(100), Timer::new);
+        let (stream_c, stream_s) = helper::duplex();
+        let client = async {
+            let mut stream = BufReader::new(stream_c);
+            converse(&mut stream, &[("", "+OK"), ("CAPA\r\n", "+OK")]).await?;
+            Ok::<_, io::Error>(stream)
+        };
+        let (server, client) = join(starttls::pop3_accept(&acceptor, stream_s), client).await;
+        assert_eq!(server.err().unwrap().kind(), io::ErrorKind::TimedOut);
+        client?;
+
+        Ok(())
+    })
+}
+
+/// Reads a request line, then writes `reply`.
+async fn mock_server(stream: helper::DuplexStream, script: &[&str]) -> io::Result<()> {
+    let mut stream = BufReader::new(stream);
+    for (i, reply) in script.iter().enumerate() {
+        if i > 0 {
+            let mut line = String::new();
+            stream.read_line(&mut line).await?;
+        }
+        stream.get_mut().write_all(reply.as_bytes()).await?;
+    }
+    Ok(())
+}
+
+#[test]
+fn mock_servers_refusing() -> io::Result<()> {
+    block_on(async {
+        let connector = connector()?;
+
+        let (stream_c, stream_s) = helper::duplex();
+        let (server, client) = join(
+            mock_server(
+                stream_s,
+                &[
+                    "220 mx.lvh.me ESMTP\r\n",
+                    "250-mx.lvh.me\r\n250 SIZE 1000\r\n",
+                ],
+            ),
+            starttls::smtp_connect(&connector, "tls.lvh.me", stream_c, "client.lvh.me"),
+        )
+        .await;
+        server?;
+        let err = client.err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("STARTTLS not advertised"));
+
+        let (stream_c, stream_s) = helper::duplex();
+        let (server, client) = join(
+            mock_server(stream_s, &["* OK ready\r\n", "a1 NO STARTTLS disabled\r\n"]),
+            starttls::imap_connect(&connector, "tls.lvh.me", stream_c),
+        )
+        .await;
+        server?;
+        assert!(client
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("a1 NO STARTTLS disabled"));
+
+        let (stream_c, stream_s) = helper::duplex();
+        let (server, client) = join(
+            mock_server(stream_s, &["+OK ready\r\n", "-ERR not supported\r\n"]),
+            starttls::pop3_connect(&connector, "tls.lvh.me", stream_c),
+        )
+        .await;
+        server?;
+        assert_eq!(client.err().unwrap().kind(), io::ErrorKind::InvalidData);
+
+        let (stream_c, mut stream_s) = helper::duplex();
+        let server = async {
+            let mut request = [0; 8];
+            stream_s.read_exact(&mut request).await?;
+            assert_eq!(request, [0, 0, 0, 8, 0x04, 0xd2, 0x16, 0x2f]);
+            stream_s.write_all(b"N").await
+        };
+        let (server, client) = join(
+            server,
+            starttls::postgres_connect(&connector, "tls.lvh.me", stream_c),
+        )
+        .await;
+        server?;
+        assert_eq!(client.err().unwrap().kind(), io::ErrorKind::InvalidData);
+
+        Ok(())
+    })
+}
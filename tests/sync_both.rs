@@ -30,7 +30,7 @@ fn tcp_stream() -> io::Result<()> {
         let (sender_s, receiver) = mpsc::channel::<String>();
         let sender_c = sender_s.clone();
 
-        let executor = ThreadPool::new()?;
+        let executor = ThreadPool::builder().pool_size(2).create()?;
 
         executor
             .spawn(async move {
@@ -42,7 +42,7 @@ fn tcp_stream() -> io::Result<()> {
                     })
                     .unwrap()
             })
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            .expect("failed to spawn");
 
         executor
             .spawn(async move {
@@ -54,7 +54,7 @@ fn tcp_stream() -> io::Result<()> {
                     })
                     .unwrap()
             })
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            .expect("failed to spawn");
 
         let msg = receiver.recv().unwrap();
         println!("receiver.recv {}", msg);